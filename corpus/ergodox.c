@@ -0,0 +1,20 @@
+  [BASE] = LAYOUT_ergodox_pretty(
+    KC_EQL,          KC_1,           KC_2,          KC_3,    KC_4,    KC_5,    KC_LEFT,              KC_RGHT,      KC_6,    KC_7,    KC_8,    KC_9,              KC_0,           KC_MINS,
+    KC_DEL,          KC_Q,           KC_W,          KC_E,    KC_R,    KC_T,    TG(SYMB),             TG(SYMB),     KC_Y,    KC_U,    KC_I,    KC_O,              KC_P,           KC_BSLS,
+    KC_BSPC,         KC_A,           KC_S,          KC_D,    KC_F,    KC_G,                                        KC_H,    KC_J,    KC_K,    KC_L,              LT(MDIA, KC_SCLN), GUI_T(KC_QUOT),
+    KC_LSFT,         CTL_T(KC_Z),    KC_X,          KC_C,    KC_V,    KC_B,    ALL_T(KC_NO),         MEH_T(KC_NO), KC_N,    KC_M,    KC_COMM, KC_DOT,            CTL_T(KC_SLSH), KC_RSFT,
+    LT(SYMB,KC_GRV), KC_QUOT,        LALT(KC_LSFT), KC_LEFT, KC_RGHT,                                                       KC_UP,   KC_DOWN, KC_LBRC,           KC_RBRC,        TT(SYMB),
+                                                                 ALT_T(KC_APP), KC_LGUI,                 KC_LALT, CTL_T(KC_ESC),
+                                                                                KC_HOME,                 KC_PGUP,
+                                                         KC_SPC, KC_BSPC,       KC_END,                  KC_PGDN, KC_TAB, KC_ENT
+  ),
+  [SYMB] = LAYOUT_ergodox_pretty(
+    VRSN,    KC_F1,   KC_F2,   KC_F3,   KC_F4,   KC_F5,   _______,     _______, KC_F6,   KC_F7,   KC_F8,   KC_F9,   KC_F10,  KC_F11,
+    _______, KC_EXLM, KC_AT,   KC_LCBR, KC_RCBR, KC_PIPE, _______,     _______, KC_UP,   KC_7,    KC_8,    KC_9,    KC_ASTR, KC_F12,
+    _______, KC_HASH, KC_DLR,  KC_LPRN, KC_RPRN, KC_GRV,                        KC_DOWN, KC_4,    KC_5,    KC_6,    KC_PLUS, _______,
+    _______, KC_PERC, KC_CIRC, KC_LBRC, KC_RBRC, KC_TILD, _______,     _______, KC_AMPR, KC_1,    KC_2,    KC_3,    KC_BSLS, _______,
+    EE_CLR,  _______, _______, _______, _______,                                         _______, KC_DOT,  KC_0,    KC_EQL,  _______,
+                                                 RGB_MOD, RGB_VAI,     RGB_TOG, RGB_SLD,
+                                                          RGB_VAD,     RGB_HUI,
+                                        RGB_HUD, _______, _______,     _______, _______, _______
+  )
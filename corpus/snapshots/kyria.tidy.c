@@ -0,0 +1,12 @@
+[_QWERTY] = LAYOUT (
+  KC_TAB,    KC_Q,    KC_W,    KC_E,    KC_R,    KC_T,                                         KC_Y     ,KC_U    ,KC_I    ,KC_O    ,KC_P    ,KC_BSPC  ,
+ CTL_ESC,    KC_A,    KC_S,    KC_D,    KC_F,    KC_G,                                         KC_H     ,KC_J    ,KC_K    ,KC_L    ,KC_SCLN ,CTL_QUOT ,
+ KC_LSFT,    KC_Z,    KC_X,    KC_C,    KC_V,    KC_B, KC_LBRC, KC_CAPS,     FKEYS   ,KC_RBRC ,KC_N     ,KC_M    ,KC_COMM ,KC_DOT  ,KC_SLSH ,KC_RSFT  ,
+                             ADJUST, KC_LGUI, ALT_ENT,  KC_SPC,     NAV,     SYM     ,KC_SPC  ,KC_RALT  ,KC_RGUI ,KC_APP                              
+),
+[_NAV] = LAYOUT (
+ _______, _______, _______, _______, _______, _______,                                         KC_PGUP  ,KC_HOME ,KC_UP   ,KC_END  ,KC_VOLU ,KC_DEL   ,
+ _______, KC_LGUI, KC_LALT, KC_LCTL, KC_LSFT, _______,                                         KC_PGDN  ,KC_LEFT ,KC_DOWN ,KC_RGHT ,KC_VOLD ,KC_INS   ,
+ _______, _______, _______, _______, _______, _______, _______, KC_SCRL,     _______ ,_______ ,KC_PAUSE ,KC_MPRV ,KC_MPLY ,KC_MNXT ,KC_MUTE ,KC_PSCR  ,
+                            _______, _______, _______, _______, _______,     _______ ,_______ ,_______  ,_______ ,_______                             
+),
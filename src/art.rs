@@ -0,0 +1,498 @@
+use easier::prelude::*;
+
+use crate::{
+    context::Context,
+    key::{nice_code, short_keycode, split_call},
+    lint::is_known_keycode,
+    myparser::Keymap,
+    options::PrintOptions,
+};
+
+///each active layer drawn as a box-drawn keyboard, under a heading
+pub fn keymap_art(keymap: &Keymap, ops: &PrintOptions) -> String {
+    let grids = keymap
+        .active_layers()
+        .into_iter()
+        .map(|l| l.active_keys().into_iter().cloned().to_vec())
+        .to_vec();
+    let widths = column_widths(&grids, &keymap.context, ops);
+    let mut result = String::new();
+    for (layer, grid) in keymap.active_layers().into_iter().zip(&grids) {
+        result.push_str(&format!("{}\n", layer.ident()));
+        result.push_str(&layer_art(grid, layer.split, &widths, &keymap.context, ops));
+        result.push('\n');
+    }
+    result
+}
+
+///widest label in each column, so every layer lines up
+pub fn column_widths(
+    grids: &[Vec<Vec<Option<String>>>],
+    ctx: &Context,
+    ops: &PrintOptions,
+) -> Vec<usize> {
+    let mut widths = vec![];
+    for row in grids.iter().flatten() {
+        for (ci, code) in row.iter().enumerate() {
+            if ci >= widths.len() {
+                widths.push(1);
+            }
+            if let Some(code) = code {
+                let (label, hold) = labels(code, ctx);
+                let mut width = label.chars().count();
+                if ops.hold_legends {
+                    width = width.max(hold.chars().count());
+                }
+                widths[ci] = widths[ci].max(width);
+            }
+        }
+    }
+    widths
+}
+
+///one layer's grid drawn with ┌─┬─┐ boxes, the halves of a split board apart by split_space
+pub fn layer_art(
+    grid: &[Vec<Option<String>>],
+    split: bool,
+    widths: &[usize],
+    ctx: &Context,
+    ops: &PrintOptions,
+) -> String {
+    let cells = grid
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|c| c.as_ref().map(|c| labels(c, ctx)))
+                .to_vec()
+        })
+        .to_vec();
+    if !split {
+        let lines = draw_boxes(&cells, widths, ops.hold_legends);
+        return lines
+            .iter()
+            .map(|l| format!("{}\n", l.trim_end()))
+            .collect();
+    }
+    let centre = widths.len() / 2;
+    let half = |range: std::ops::Range<usize>| {
+        let cells = cells
+            .iter()
+            .map(|row| row.get(range.clone()).unwrap_or_default().to_vec())
+            .to_vec();
+        draw_boxes(&cells, &widths[range], ops.hold_legends)
+    };
+    let left = half(0..centre);
+    let right = half(centre..widths.len());
+    let gap = " ".repeat(ops.split_space);
+    let mut result = String::new();
+    for (l, r) in left.iter().zip(&right) {
+        result.push_str(format!("{l}{gap}{r}").trim_end());
+        result.push('\n');
+    }
+    result
+}
+
+//the tap label and the hold label underneath
+fn labels(code: &str, ctx: &Context) -> (String, String) {
+    let nice = nice_code(code, ctx);
+    (nice.middle, nice.bottom)
+}
+
+//shared borders, the corners picked by which neighbouring keys exist
+fn draw_boxes(
+    cells: &[Vec<Option<(String, String)>>],
+    widths: &[usize],
+    holds: bool,
+) -> Vec<String> {
+    let present = |r: usize, c: usize| -> bool {
+        r > 0
+            && c > 0
+            && matches!(
+                cells.get(r - 1).and_then(|row| row.get(c - 1)),
+                Some(Some(_))
+            )
+    };
+    //rows and columns are offset by one so the edges have a row before them
+    let mut lines = vec![];
+    for b in 0..=cells.len() {
+        let mut line = String::new();
+        for c in 0..=widths.len() {
+            let (ul, ur, dl, dr) = (
+                present(b, c),
+                present(b, c + 1),
+                present(b + 1, c),
+                present(b + 1, c + 1),
+            );
+            line.push(junction(ul || ur, dl || dr, ul || dl, ur || dr));
+            if c < widths.len() {
+                let edge = if ur || dr { '─' } else { ' ' };
+                line.push_str(&edge.to_string().repeat(widths[c] + 2));
+            }
+        }
+        lines.push(line);
+        if b == cells.len() {
+            break;
+        }
+        //the tap labels, then the hold labels
+        for hold in [false, true].into_iter().take(if holds { 2 } else { 1 }) {
+            let mut line = String::new();
+            for c in 0..=widths.len() {
+                let edge = present(b + 1, c) || present(b + 1, c + 1);
+                line.push(if edge { '│' } else { ' ' });
+                if c < widths.len() {
+                    let text = match &cells[b][c] {
+                        Some((tap, held)) => {
+                            if hold {
+                                held
+                            } else {
+                                tap
+                            }
+                        }
+                        None => "",
+                    };
+                    line.push_str(&format!(" {:^w$} ", text, w = widths[c]));
+                }
+            }
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+fn junction(up: bool, down: bool, left: bool, right: bool) -> char {
+    match (up, down, left, right) {
+        (true, true, true, true) => '┼',
+        (false, true, true, true) => '┬',
+        (true, false, true, true) => '┴',
+        (true, true, false, true) => '├',
+        (true, true, true, false) => '┤',
+        (false, true, false, true) => '┌',
+        (false, true, true, false) => '┐',
+        (true, false, false, true) => '└',
+        (true, false, true, false) => '┘',
+        (false, false, false, false) => ' ',
+        (false, false, _, _) => '─',
+        (_, _, false, false) => '│',
+    }
+}
+
+///the last /* */ comment in the text, if it draws keys
+pub fn art_comment(text: &str) -> Option<String> {
+    let start = text.rfind("/*")?;
+    let end = text[start..].find("*/")? + start + 2;
+    let comment = &text[start..end];
+    (!parse_art(comment).is_empty()).then(|| comment.to_string())
+}
+
+///the tap labels in each row of a box-drawing comment, e.g. ┌───┐ or ,-----.
+///with how many keys each covers going by the junctions above and below it, e.g. 2 for a 2u space bar
+pub fn parse_art(comment: &str) -> Vec<Vec<(String, usize)>> {
+    let lines = comment.lines().map(|l| l.chars().to_vec()).to_vec();
+    let is_bar = |c: &char| "│|┃║".contains(*c);
+    let is_edge = |c: &char| "─-=━═".contains(*c);
+    let is_border = |line: &Vec<char>| {
+        line.iter().any(is_edge)
+            && line
+                .iter()
+                .all(|c| c.is_whitespace() || "─-=━═┌┐└┘├┤┬┴┼│|+,.`'╭╮╯╰*/┃║".contains(*c))
+    };
+    let is_content =
+        |line: &Vec<char>| line.iter().filter(|c| is_bar(c)).count() >= 2 && !is_border(line);
+    let edge_at =
+        |line: Option<&Vec<char>>, col: usize| line.and_then(|l| l.get(col)).is_some_and(is_edge);
+    let is_junction = |c: &char| "+┼┬┴╋┳┻|│".contains(*c);
+    let junction_at = |line: Option<&Vec<char>>, col: usize| {
+        line.and_then(|l| l.get(col)).is_some_and(is_junction)
+    };
+    let junctions = |line: Option<&Vec<char>>, from: usize, to: usize| {
+        line.map_or(0, |l| {
+            l.iter()
+                .take(to)
+                .skip(from)
+                .filter(|c| is_junction(c))
+                .count()
+        })
+    };
+
+    let mut rows = vec![];
+    for (li, line) in lines.iter().enumerate() {
+        //only the first line under a border has the tap labels, the rest are holds
+        if !is_content(line) || li.checked_sub(1).is_some_and(|p| is_content(&lines[p])) {
+            continue;
+        }
+        let above = lines[..li].iter().rev().find(|l| is_border(l));
+        let below = lines[li + 1..].iter().find(|l| is_border(l));
+        //a | legend has borders running over and under it, where the other walls meet a junction
+        let all_bars = line
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| is_bar(c))
+            .map(|(i, _)| i)
+            .to_vec();
+        let inner = all_bars
+            .get(1..all_bars.len().saturating_sub(1))
+            .unwrap_or_default();
+        let marks_walls = inner
+            .iter()
+            .any(|i| junction_at(above, *i) || junction_at(below, *i));
+        let bars = all_bars
+            .iter()
+            .copied()
+            .filter(|i| {
+                !(marks_walls && inner.contains(i) && edge_at(above, *i) && edge_at(below, *i))
+            })
+            .to_vec();
+        //a gap between two boards has no border over it
+        let row = bars
+            .windows(2)
+            .filter(|w| edge_at(above, (w[0] + w[1]) / 2) || edge_at(below, (w[0] + w[1]) / 2))
+            .map(|w| {
+                let label = line[w[0] + 1..w[1]]
+                    .iter()
+                    .collect::<String>()
+                    .trim()
+                    .to_string();
+                let span = junctions(above, w[0] + 1, w[1]).max(junctions(below, w[0] + 1, w[1]));
+                (label, span + 1)
+            })
+            .to_vec();
+        if !row.is_empty() {
+            rows.push(row);
+        }
+    }
+    rows
+}
+
+///where the box-drawing comment above a layer disagrees with its keycodes
+pub fn check_art(keymap: &Keymap) -> Vec<String> {
+    let mut warnings = vec![];
+    for layer in keymap.active_layers() {
+        let Some(art) = &layer.art else {
+            continue;
+        };
+        let art_rows = parse_art(art);
+        let rows = layer
+            .active_keys()
+            .into_iter()
+            .map(|row| row.iter().flatten().to_vec())
+            .to_vec();
+        if art_rows.len() != rows.len() {
+            warnings.push(format!(
+                "Layer {}: comment has {} rows of keys, layer has {}",
+                layer.num,
+                art_rows.len(),
+                rows.len()
+            ));
+            continue;
+        }
+        for (ri, (art_row, row)) in art_rows.iter().zip(&rows).enumerate() {
+            //a label per key, or labels over several keys when that adds up
+            let labels = if art_row.len() == row.len() {
+                art_row.iter().map(|(label, _)| label).to_vec()
+            } else if art_row.iter().map(|(_, span)| span).sum::<usize>() == row.len() {
+                art_row
+                    .iter()
+                    .flat_map(|(label, span)| std::iter::repeat_n(label, *span))
+                    .to_vec()
+            } else {
+                warnings.push(format!(
+                    "Layer {} row {}: comment has {} keys, layer has {}",
+                    layer.num,
+                    ri + 1,
+                    art_row.len(),
+                    row.len()
+                ));
+                continue;
+            };
+            for (label, code) in labels.into_iter().zip(row) {
+                if !label_matches(label, code, &keymap.context) {
+                    warnings.push(format!(
+                        "Layer {}: comment says \"{label}\" where the keymap has {code}",
+                        layer.num
+                    ));
+                }
+            }
+        }
+    }
+    warnings
+}
+
+//common legends that aren't a keycode name, and names they stand for
+const ALIASES: &[(&str, &[&str])] = &[
+    ("bksp", &["bspc"]),
+    ("backspace", &["bspc"]),
+    ("shift", &["lsft", "rsft"]),
+    ("enter", &["ent"]),
+    ("return", &["ent"]),
+    ("space", &["spc"]),
+    ("pg up", &["pgup"]),
+    ("pg dn", &["pgdn"]),
+    ("page up", &["pgup"]),
+    ("page down", &["pgdn"]),
+    ("ctrl", &["lctl", "rctl"]),
+    ("alt", &["lalt", "ralt"]),
+    ("gui", &["lgui", "rgui"]),
+    ("right", &["rght"]),
+    ("next", &["mnxt"]),
+    ("prev", &["mprv"]),
+    ("play", &["mply"]),
+    ("mute", &["mute"]),
+    ("vol-", &["vold"]),
+    ("vol+", &["volu"]),
+    ("iso #", &["nuhs"]),
+    ("iso ~", &["nuhs"]),
+    ("iso /", &["nubs"]),
+    ("iso |", &["nubs"]),
+    ("iso \\", &["nubs"]),
+];
+
+//lenient, "Tab", "TAB", "KC_TAB", "⇥", "Bksp" or "A/Sft" all match their keys
+fn label_matches(label: &str, code: &str, ctx: &Context) -> bool {
+    let label = label.to_lowercase();
+    let expanded = ctx.expand(code);
+    let nice = nice_code(code, ctx);
+    if matches!(expanded.as_str(), "KC_TRNS" | "_______" | "KC_TRANSPARENT")
+        && (label.is_empty()
+            || label.chars().all(|c| c == '_')
+            || ["▽", "⇄", "trns"].contains(&label.as_str()))
+    {
+        return true;
+    }
+    //lint warns about keycodes we don't know, so their labels can't be checked
+    if !expanded.contains('(') && !expanded.starts_with('&') && !is_known_keycode(&expanded, ctx) {
+        return true;
+    }
+    if label.is_empty() {
+        return nice.middle.is_empty() || matches!(expanded.as_str(), "KC_NO" | "XXXXXXX");
+    }
+    let mut codes = vec![code.to_string(), expanded.clone()];
+    if let Some((_, params)) = split_call(&expanded) {
+        codes.extend(params);
+    }
+    let mut candidates = vec![nice.middle, nice.top, nice.bottom];
+    for code in codes {
+        candidates.push(short_keycode(&code).to_string());
+        candidates.push(code.trim_start_matches("KC_").to_string());
+        candidates.push(short_keycode(&code).trim_start_matches("KC_").to_string());
+        candidates.push(code);
+    }
+    let candidates = candidates
+        .into_iter()
+        .filter(|c| !c.is_empty())
+        .map(|c| c.to_lowercase())
+        .to_vec();
+    std::iter::once(label.as_str())
+        .chain(label.split(|c: char| c.is_whitespace() || c == '/'))
+        .flat_map(|token| {
+            let aliases = ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == token)
+                .map_or(&[][..], |(_, names)| *names);
+            std::iter::once(token).chain(aliases.iter().copied())
+        })
+        .any(|token| candidates.iter().any(|c| c == token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::myparser::{get_keymap, keymap_string, MyParser, Rule};
+    use pest::Parser;
+
+    #[test]
+    fn box_art() {
+        let example = r#"
+            [0] = LAYOUT(KC_Q, LSFT_T(KC_A), KC_S, KC_D,
+                KC_1, MO(1)),
+        "#;
+        let ops = PrintOptions {
+            split_space: 2,
+            hold_legends: true,
+            ..Default::default()
+        };
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        let keymap = get_keymap(prog.next().unwrap(), &ops).unwrap();
+        assert_eq!(
+            keymap_art(&keymap, &ops),
+            r#"layer_0
+┌───┬───┐              ┌───┬───┐
+│ Q │ A │              │ S │ D │
+│   │ ⇧ │              │   │   │
+└───┴───┼───┐  ┌───────┼───┴───┘
+        │ 1 │  │ MO(1) │
+        │   │  │       │
+        └───┘  └───────┘
+
+"#
+        );
+        let ops = PrintOptions {
+            draw_nice: true,
+            box_art: true,
+            ..Default::default()
+        };
+        assert!(keymap_string(&keymap, &ops).starts_with(
+            r#"/*
+┌───┬───┐                 ┌───┬───┐
+│ Q │ A │                 │ S │ D │
+└───┴───┼───┐     ┌───────┼───┴───┘
+        │ 1 │     │ MO(1) │
+        └───┘     └───────┘
+*/
+[0] = LAYOUT (
+"#
+        ));
+    }
+
+    fn parse(example: &str, ops: &PrintOptions) -> Keymap {
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        get_keymap(prog.next().unwrap(), ops).unwrap()
+    }
+
+    #[test]
+    fn stale_comment() {
+        let example = r#"
+/*
+ * ,-----------------------------.           ,-----------------------------.
+ * | Tab  |   Q  | A/Sft|   W    |           |   S  |   D  |   F  |  Bspc  |
+ * |------+------+------+--------|           |------+------+------+--------|
+ *                      | LOWER  |           |  Spc |
+ *                      `--------'           `------'
+ */
+            [0] = LAYOUT(KC_TAB, KC_Q, LSFT_T(KC_A), KC_E, KC_S, KC_D, KC_F, KC_BSPC,
+                MO(1), KC_SPC),
+        "#;
+        let ops = PrintOptions::default();
+        let keymap = parse(example, &ops);
+        assert_eq!(
+            check_art(&keymap),
+            vec![
+                "Layer 0: comment says \"W\" where the keymap has KC_E",
+                "Layer 0: comment says \"LOWER\" where the keymap has MO(1)",
+            ]
+        );
+        //kept as it is, or redrawn
+        assert!(keymap_string(&keymap, &ops).starts_with("/*\n * ,----"));
+        let ops = PrintOptions {
+            box_art: true,
+            ..Default::default()
+        };
+        let tidy = keymap_string(&keymap, &ops);
+        assert!(tidy.starts_with("/*\n┌"));
+        assert!(check_art(&parse(&tidy, &ops)).is_empty());
+    }
+
+    #[test]
+    fn planck_comments() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/corpus/planck.c");
+        let example = std::fs::read_to_string(path).unwrap();
+        let ops = PrintOptions::default();
+        let mut keymap = parse(&example, &ops);
+        keymap.context = Context::from_source(&example);
+        assert_eq!(check_art(&keymap), Vec::<String>::new());
+        //one row has no junctions to tell its walls from | legends
+        assert_eq!(
+            parse_art("/*\n * ,---------.\n * | A  | B |\n * `---------'\n */"),
+            vec![vec![("A".to_string(), 1), ("B".to_string(), 1)]]
+        );
+    }
+}
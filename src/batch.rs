@@ -0,0 +1,176 @@
+use easier::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    config::{file_options, Flags},
+    error::MyError,
+    myparser::tidy_file,
+};
+
+pub enum Outcome {
+    Changed,
+    Unchanged,
+    Failed(String),
+}
+
+///every keymap.c under a qmk_firmware or userspace folder, e.g. keyboards/crkbd/keymaps/me/keymap.c
+pub fn keymap_files(dir: &Path) -> Result<Vec<PathBuf>, MyError> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries =
+            std::fs::read_dir(&dir).map_err(|e| format!("Can't read {}: {e}", dir.display()))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            //.git and .build
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            //not through symlinks, which can point back up the tree
+            let file_type = entry
+                .file_type()
+                .map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+            if file_type.is_dir() {
+                dirs.push(path);
+            } else if entry.file_name() == "keymap.c" {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+///tidies each file, writing it back if write is set
+pub fn tidy_files(files: &[PathBuf], flags: &Flags, write: bool) -> Vec<(PathBuf, Outcome)> {
+    files
+        .iter()
+        .map(|file| {
+            let outcome = match tidy_one(file, flags, write) {
+                Ok(true) => Outcome::Changed,
+                Ok(false) => Outcome::Unchanged,
+                Err(e) => Outcome::Failed(e.to_string()),
+            };
+            (file.clone(), outcome)
+        })
+        .to_vec()
+}
+
+//whether the file is, or would be, changed
+fn tidy_one(file: &Path, flags: &Flags, write: bool) -> Result<bool, MyError> {
+    let source =
+        std::fs::read_to_string(file).map_err(|e| format!("Can't read {}: {e}", file.display()))?;
+    let ops = file_options(file, &source, flags)?;
+    let tidy = tidy_file(&source, &ops)?;
+    if tidy == source {
+        return Ok(false);
+    }
+    if write {
+        std::fs::write(file, tidy).map_err(|e| format!("Can't write {}: {e}", file.display()))?;
+    }
+    Ok(true)
+}
+
+///a line per file, with the reason under each failure, then the totals
+pub fn summary(results: &[(PathBuf, Outcome)], write: bool) -> String {
+    let changed = if write { "changed" } else { "would change" };
+    let mut result = String::new();
+    let mut counts = [0; 3];
+    for (file, outcome) in results {
+        match outcome {
+            Outcome::Changed => {
+                counts[0] += 1;
+                result.push_str(&format!("{changed:<12} {}\n", file.display()));
+            }
+            Outcome::Unchanged => {
+                counts[1] += 1;
+                result.push_str(&format!("{:<12} {}\n", "unchanged", file.display()));
+            }
+            Outcome::Failed(reason) => {
+                counts[2] += 1;
+                result.push_str(&format!("{:<12} {}\n", "failed", file.display()));
+                for line in reason.lines() {
+                    result.push_str(&format!("    {line}\n"));
+                }
+            }
+        }
+    }
+    result.push_str(&format!(
+        "{} {changed}, {} unchanged, {} failed\n",
+        counts[0], counts[1], counts[2]
+    ));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch() {
+        let root = std::env::temp_dir().join(format!("tidyqmk-batch-{}", std::process::id()));
+        let keymap = |keyboard: &str, layers: &str| {
+            let folder = root.join(format!("keyboards/{keyboard}/keymaps/me"));
+            std::fs::create_dir_all(&folder).unwrap();
+            let text = format!(
+                "const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {{{layers}}};\n"
+            );
+            std::fs::write(folder.join("keymap.c"), text).unwrap();
+        };
+        keymap("a", "\n[0] = LAYOUT(KC_A,KC_B,\nKC_C,KC_D)\n");
+        keymap("b", "\n[0] = LAYOUT(KC_A, KC_B),\n[1] = LAYOUT(KC_A)\n");
+        keymap(
+            "c",
+            "[0]=LAYOUT(KC_A,KC_B,\nKC_C,KC_D),[1]=LAYOUT(KC_A,KC_B,KC_C,\nKC_D)",
+        );
+        keymap(
+            "d",
+            "\n// base\n[0] = LAYOUT(KC_A,KC_B, // top\n// bottom\nKC_C,KC_D)\n// end\n",
+        );
+        let inside = "\n[0] = LAYOUT(KC_A, /* left */ KC_B,\nKC_C,KC_D)\n";
+        keymap("e", inside);
+        std::fs::create_dir_all(root.join(".build/keymaps/me")).unwrap();
+        std::fs::write(root.join(".build/keymaps/me/keymap.c"), "").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&root, root.join("keyboards/a/keymaps/up")).unwrap();
+
+        let files = keymap_files(&root).unwrap();
+        assert_eq!(files.len(), 5);
+        let first = std::fs::read_to_string(&files[0]).unwrap();
+        let results = tidy_files(&files, &Flags::default(), false);
+        assert_eq!(std::fs::read_to_string(&files[0]).unwrap(), first);
+        let summary = summary(&results, false);
+        assert!(summary.starts_with("would change"));
+        assert!(summary
+            .contains("b/keymaps/me/keymap.c\n    All layers must have the same number of keys\n"));
+        assert!(summary
+            .lines()
+            .any(|l| l.starts_with("would change") && l.ends_with("c/keymaps/me/keymap.c")));
+        assert!(summary.contains("e/keymaps/me/keymap.c\n    Can't keep the comment /* left */"));
+        assert!(summary.ends_with("3 would change, 0 unchanged, 2 failed\n"));
+
+        tidy_files(&files, &Flags::default(), true);
+        let commented = std::fs::read_to_string(&files[3]).unwrap();
+        for comment in [
+            "    // base\n",
+            "KC_B , // top\n",
+            "    // bottom\n",
+            "    // end\n",
+        ] {
+            assert!(
+                commented.contains(comment),
+                "{comment} lost from:\n{commented}"
+            );
+        }
+        assert!(std::fs::read_to_string(&files[4]).unwrap().contains(inside));
+        let results = tidy_files(
+            &[files[0].clone(), files[2].clone(), files[3].clone()],
+            &Flags::default(),
+            true,
+        );
+        assert!(results
+            .iter()
+            .all(|(_, outcome)| matches!(outcome, Outcome::Unchanged)));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
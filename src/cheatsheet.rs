@@ -0,0 +1,200 @@
+use easier::prelude::*;
+
+use crate::{
+    key::{nice_code, PrintKey},
+    myparser::{Keymap, Layer},
+    options::PrintOptions,
+};
+
+///a markdown table for each layer, with an empty column for the split if there is one
+pub fn markdown_tables(keymap: &Keymap) -> String {
+    let mut result = String::new();
+    for layer in keymap.active_layers() {
+        let grid = cells(layer, keymap);
+        let Some(first) = grid.first() else {
+            continue;
+        };
+        result.push_str(&format!("### {}\n\n", layer_title(layer)));
+        //github needs a header row, so it is left blank
+        //one more column for the split
+        let width = first.len() + usize::from(layer.split);
+        let centre = first.len() / 2;
+        result.push_str(&format!("|{}\n", " |".repeat(width)));
+        result.push_str(&format!("|{}\n", "---|".repeat(width)));
+        for row in &grid {
+            let mut row = row
+                .iter()
+                .map(|cell| match cell {
+                    Some(key) => markdown_key(key),
+                    None => String::new(),
+                })
+                .to_vec();
+            if layer.split {
+                row.insert(centre, String::new());
+            }
+            result.push_str(&format!("| {} |\n", row.join(" | ")));
+        }
+        result.push('\n');
+    }
+    result
+}
+
+///a standalone html page with a css grid for each layer
+pub fn html_page(keymap: &Keymap, ops: &PrintOptions) -> String {
+    let mut result = String::from(HTML_HEAD);
+    for layer in keymap.active_layers() {
+        let grid = cells(layer, keymap);
+        let Some(first) = grid.first() else {
+            continue;
+        };
+        //the split is its own column, sized by split_space
+        let centre = first.len() / 2;
+        let template = if layer.split {
+            format!(
+                "repeat({}, 4em) {}em repeat({}, 4em)",
+                centre,
+                ops.split_space,
+                first.len() - centre
+            )
+        } else {
+            format!("repeat({}, 4em)", first.len())
+        };
+        result.push_str(&format!("<h2>{}</h2>\n", escape_html(&layer_title(layer))));
+        result.push_str(&format!(
+            "<div class=\"layer\" style=\"grid-template-columns: {template}\">\n"
+        ));
+        for (ri, row) in grid.iter().enumerate() {
+            for (ci, cell) in row.iter().enumerate() {
+                let Some(key) = cell else {
+                    continue;
+                };
+                //skip the split column
+                let column = if layer.split && ci >= centre {
+                    ci + 2
+                } else {
+                    ci + 1
+                };
+                result.push_str(&format!(
+                    "<div class=\"key\" style=\"grid-row: {}; grid-column: {}\">\
+                     <span class=\"top\">{}</span><span>{}</span><span class=\"bottom\">{}</span></div>\n",
+                    ri + 1,
+                    column,
+                    escape_html(&key.top),
+                    escape_html(&key.middle),
+                    escape_html(&key.bottom)
+                ));
+            }
+        }
+        result.push_str("</div>\n");
+    }
+    result.push_str("</body>\n</html>\n");
+    result
+}
+
+const HTML_HEAD: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<style>
+body { font-family: sans-serif; background: #1e1e1e; color: white; }
+.layer { display: grid; grid-auto-rows: 4em; gap: 0.3em; margin-bottom: 2em; }
+.key { display: flex; flex-direction: column; justify-content: space-between; align-items: center;
+  background: #2c2c2c; border: 2px solid #555555; border-radius: 0.5em; padding: 0.2em; }
+.top, .bottom { font-size: 0.7em; min-height: 1em; }
+</style>
+</head>
+<body>
+"#;
+
+//the same heading as the svg
+fn layer_title(layer: &Layer) -> String {
+    if layer.num.len() == 1 {
+        format!("Layer {}", layer.num)
+    } else {
+        layer.num.clone()
+    }
+}
+
+//the grid from create_grid, with labels instead of codes
+fn cells(layer: &Layer, keymap: &Keymap) -> Vec<Vec<Option<PrintKey>>> {
+    layer
+        .active_keys()
+        .into_iter()
+        .map(|row| {
+            row.iter()
+                .map(|code| code.as_ref().map(|c| nice_code(c, &keymap.context)))
+                .to_vec()
+        })
+        .to_vec()
+}
+
+//shifted and held legends go on their own lines in the cell
+fn markdown_key(key: &PrintKey) -> String {
+    [&key.top, &key.middle, &key.bottom]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .map(|s| escape_markdown(s))
+        .to_vec()
+        .join("<br>")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut result = String::new();
+    for c in escape_html(text).chars() {
+        if "\\|*_`[]#".contains(c) {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::myparser::{get_keymap, MyParser, Rule};
+    use pest::Parser;
+
+    #[test]
+    fn cheatsheet() {
+        let example = r#"
+            [0] = LAYOUT(KC_Q, LSFT_T(KC_A), KC_BSLS, KC_S,
+                KC_1, MO(1)),
+            [1] = LAYOUT(KC_LT, KC_NO, KC_TRNS, KC_B,
+                KC_2, KC_TRNS),
+        "#;
+        let ops = PrintOptions::default();
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        let keymap = get_keymap(prog.next().unwrap(), &ops).unwrap();
+        assert_eq!(
+            markdown_tables(&keymap),
+            r#"### Layer 0
+
+| | | | | | | |
+|---|---|---|---|---|---|---|
+| Q | A<br>⇧ |  |  |  | \|<br>\\ | S |
+|  |  | !<br>1 |  | MO(1) |  |  |
+
+### Layer 1
+
+| | | | | | | |
+|---|---|---|---|---|---|---|
+| &lt; |  |  |  |  | ⇄ | B |
+|  |  | @<br>2 |  | ⇄ |  |  |
+
+"#
+        );
+        let html = html_page(&keymap, &ops);
+        assert!(html.contains("grid-template-columns: repeat(3, 4em) 5em repeat(3, 4em)"));
+        assert!(html.contains(
+            "<div class=\"key\" style=\"grid-row: 2; grid-column: 3\"><span class=\"top\">!</span>"
+        ));
+    }
+}
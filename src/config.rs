@@ -0,0 +1,116 @@
+use easier::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    error::MyError,
+    options::PrintOptions,
+    profiles::{apply, layout_name, profile_options, Value},
+};
+
+///committed next to keymaps, like rustfmt.toml
+pub const CONFIG_FILE: &str = ".tidyqmk.toml";
+
+///settings from the command line, which win over the config file
+#[derive(Default)]
+pub struct Flags {
+    ///instead of searching for .tidyqmk.toml
+    pub config: Option<PathBuf>,
+    ///instead of the folder under keyboards/
+    pub keyboard: Option<String>,
+    ///e.g. ("thumb_shift_in", "2"), named as in .tidyqmk.toml
+    pub settings: Vec<(String, String)>,
+}
+
+///the nearest .tidyqmk.toml in the folder of the file or any folder above it
+pub fn find_config(file: &Path) -> Option<PathBuf> {
+    let file = std::fs::canonicalize(file).unwrap_or(file.to_path_buf());
+    file.ancestors()
+        .skip(1)
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|config| config.is_file())
+}
+
+///e.g. crkbd/rev1 from keyboards/crkbd/rev1/keymaps/me/keymap.c, in qmk_firmware or a userspace
+pub fn keyboard_name(file: &Path) -> String {
+    let file = std::fs::canonicalize(file).unwrap_or(file.to_path_buf());
+    let parts = file
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .to_vec();
+    let Some(start) = parts.iter().rposition(|p| p == "keyboards") else {
+        return String::new();
+    };
+    match parts[start..].iter().position(|p| p == "keymaps") {
+        Some(end) => parts[start + 1..start + end].join("/"),
+        None => String::new(),
+    }
+}
+
+///defaults, then the built-in profile, then .tidyqmk.toml, then the flags
+pub fn file_options(file: &Path, source: &str, flags: &Flags) -> Result<PrintOptions, MyError> {
+    let config = match flags.config.clone().or_else(|| find_config(file)) {
+        Some(path) => std::fs::read_to_string(&path)
+            .map_err(|e| format!("Can't read {}: {e}", path.display()))?,
+        None => String::new(),
+    };
+    let keyboard = flags.keyboard.clone().unwrap_or(keyboard_name(file));
+    let layout = layout_name(source).unwrap_or_default();
+    let mut ops = profile_options(&PrintOptions::default(), &config, &layout, &keyboard)?;
+    let settings = flags
+        .settings
+        .iter()
+        .map(|(key, value)| (key.clone(), flag_value(value)))
+        .to_vec();
+    apply(&mut ops, &settings)?;
+    Ok(ops)
+}
+
+//what the value would be in .tidyqmk.toml, without needing quotes
+fn flag_value(text: &str) -> Value {
+    match text {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ if !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()) => {
+            Value::Number(text.to_string())
+        }
+        _ => Value::Text(text.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discovery() {
+        let root = std::env::temp_dir().join(format!("tidyqmk-config-{}", std::process::id()));
+        let folder = root.join("keyboards/crkbd/rev1/keymaps/me");
+        std::fs::create_dir_all(&folder).unwrap();
+        let file = folder.join("keymap.c");
+        std::fs::write(&file, "").unwrap();
+        std::fs::write(
+            root.join(CONFIG_FILE),
+            "split_space = 3\n[profile.crkbd]\nthumb_shift_in = 2\n",
+        )
+        .unwrap();
+
+        assert_eq!(keyboard_name(&file), "crkbd/rev1");
+        assert_eq!(
+            find_config(&file),
+            Some(std::fs::canonicalize(root.join(CONFIG_FILE)).unwrap())
+        );
+        let flags = Flags {
+            settings: vec![
+                ("split_space".to_string(), "4".to_string()),
+                ("draw_nice".to_string(), "true".to_string()),
+            ],
+            ..Flags::default()
+        };
+        let ops = file_options(&file, "", &flags).unwrap();
+        assert_eq!(
+            (ops.split_space, ops.thumb_shift_in, ops.draw_nice),
+            (4, 2, true)
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
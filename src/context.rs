@@ -0,0 +1,466 @@
+use std::collections::HashMap;
+
+use easier::prelude::*;
+use pest::{iterators::Pair, Parser};
+
+use crate::{
+    myparser::{MyParser, Rule},
+    tables::{get_table, Table, TableBody, Value},
+    zmk::qmk_code,
+};
+
+/// What we know about the rest of keymap.c, used to label keys
+#[derive(Default)]
+pub struct Context {
+    pub tap_dances: HashMap<String, TapDance>,
+    ///user keycodes from enum custom_keycodes, which are valid even though QMK doesn't know them
+    pub custom_keycodes: Vec<String>,
+    ///friendly labels for custom keycodes e.g. MY_COPY = SAFE_RANGE, // label: Copy
+    pub labels: HashMap<String, String>,
+    ///what SEND_STRING types for a keycode in process_record_user
+    pub send_strings: HashMap<String, String>,
+    ///#define aliases, expanded when labelling and checking keys
+    pub defines: HashMap<String, Define>,
+    ///combos from key_combos
+    pub combos: Vec<Combo>,
+}
+
+pub struct Combo {
+    pub keys: Vec<String>,
+    pub result: String,
+}
+
+pub struct Define {
+    pub params: Option<Vec<String>>,
+    pub body: String,
+}
+
+pub enum TapDance {
+    Double(String, String),
+    LayerMove(String, String),
+    LayerToggle(String, String),
+    Other(String),
+}
+
+impl Context {
+    ///scan the rest of keymap.c, ignoring anything we don't understand
+    pub fn from_source(source: &str) -> Context {
+        let mut context = Context::default();
+        let Ok(pairs) = MyParser::parse(Rule::sourceouter, source) else {
+            return context;
+        };
+        for pair in pairs.clone() {
+            match pair.as_rule() {
+                Rule::cenum => context.add_enum(pair),
+                Rule::sendcase => context.add_send_string(pair),
+                Rule::define => context.add_define(pair),
+                _ => {}
+            }
+        }
+        //a table with comments that can't be tidied still has keys to label
+        let tables = pairs
+            .filter(|pair| pair.as_rule() == Rule::table)
+            .filter_map(|pair| get_table(pair).ok())
+            .to_vec();
+        //arrays of keys, e.g. const uint16_t PROGMEM esc_combo[] = {KC_E, KC_W, COMBO_END};
+        let arrays = tables
+            .iter()
+            .filter_map(|table| {
+                let name = table.declaration.split_whitespace().last()?;
+                let name = name.strip_suffix("[]")?;
+                let TableBody::List(entries) = &table.body else {
+                    return None;
+                };
+                let keys = entries
+                    .iter()
+                    .filter_map(|e| match &e.value {
+                        Value::Name(key) if key != "COMBO_END" => Some(key.clone()),
+                        _ => None,
+                    })
+                    .to_vec();
+                Some((name.to_string(), keys))
+            })
+            .collect::<HashMap<_, _>>();
+        for table in tables {
+            if table.declaration.contains("tap_dance_actions") {
+                context.add_tap_dances(table);
+            } else if table.declaration.contains("key_combos") {
+                context.add_combos(table, &arrays);
+            }
+        }
+        context
+    }
+
+    fn add_tap_dances(&mut self, table: Table) {
+        let TableBody::List(entries) = table.body else {
+            return;
+        };
+        for entry in entries {
+            let (Some(index), Value::Call(name, params)) = (entry.index, entry.value) else {
+                continue;
+            };
+            let dance = match (name.as_str(), params.as_slice()) {
+                ("ACTION_TAP_DANCE_DOUBLE", [tap, double]) => {
+                    TapDance::Double(tap.clone(), double.clone())
+                }
+                ("ACTION_TAP_DANCE_LAYER_MOVE", [tap, layer]) => {
+                    TapDance::LayerMove(tap.clone(), layer.clone())
+                }
+                ("ACTION_TAP_DANCE_LAYER_TOGGLE", [tap, layer]) => {
+                    TapDance::LayerToggle(tap.clone(), layer.clone())
+                }
+                _ => TapDance::Other(name),
+            };
+            self.tap_dances.insert(index, dance);
+        }
+    }
+
+    //COMBO(esc_combo, KC_ESC) entries, with their keys from the esc_combo array
+    fn add_combos(&mut self, table: Table, arrays: &HashMap<String, Vec<String>>) {
+        let TableBody::List(entries) = table.body else {
+            return;
+        };
+        for entry in entries {
+            let Value::Call(name, params) = entry.value else {
+                continue;
+            };
+            if let ("COMBO", [keys, result]) = (name.as_str(), params.as_slice()) {
+                if let Some(keys) = arrays.get(keys) {
+                    self.combos.push(Combo {
+                        keys: keys.clone(),
+                        result: result.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    ///expand #define aliases in a key, e.g. HOME_A to LGUI_T(KC_A)
+    ///only if the result is still a key we understand
+    pub fn expand(&self, code: &str) -> String {
+        //ZMK bindings e.g. &kp Q are read as their QMK equivalent
+        let code = &qmk_code(code).unwrap_or_else(|| code.to_string());
+        if self.defines.is_empty() {
+            return code.to_string();
+        }
+        let mut current = code.to_string();
+        //limit in case of defines referring to each other
+        for _ in 0..16 {
+            let next = self.expand_once(&current);
+            if next == current {
+                break;
+            }
+            current = next;
+        }
+        let current = current.trim().to_string();
+        match MyParser::parse(Rule::key, &current) {
+            Ok(pairs) if pairs.as_str() == current => current,
+            _ => code.to_string(),
+        }
+    }
+
+    fn expand_once(&self, text: &str) -> String {
+        let chars = text.chars().to_vec();
+        let mut result = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            if !(chars[i].is_ascii_alphabetic() || chars[i] == '_') {
+                result.push(chars[i]);
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let name = chars[start..i].iter().collect::<String>();
+            match self.defines.get(&name) {
+                Some(Define { params: None, body }) => result.push_str(body),
+                Some(Define {
+                    params: Some(params),
+                    body,
+                }) if chars.get(i) == Some(&'(') => match call_args(&chars, i) {
+                    Some((args, end)) if args.len() == params.len() => {
+                        let body = replace_idents(body, |id| {
+                            params.iter().position(|p| p == id).map(|p| args[p].clone())
+                        });
+                        result.push_str(&body.replace("##", ""));
+                        i = end;
+                    }
+                    _ => result.push_str(&name),
+                },
+                _ => result.push_str(&name),
+            }
+        }
+        result
+    }
+
+    fn add_define(&mut self, pair: Pair<Rule>) {
+        let mut name = String::new();
+        let mut params = None;
+        let mut body = String::new();
+        for part in pair.into_inner() {
+            match part.as_rule() {
+                Rule::validname => name = part.as_str().to_string(),
+                Rule::defineargs => {
+                    params = Some(part.into_inner().map(|p| p.as_str().to_string()).to_vec())
+                }
+                Rule::definebody => body = part.as_str().to_string(),
+                _ => {}
+            }
+        }
+        //remove line continuations and comments
+        let body = body.replace("\\\r\n", " ").replace("\\\n", " ");
+        let body = body.split("//").next().unwrap().split("/*").next().unwrap();
+        self.defines.insert(
+            name,
+            Define {
+                params,
+                body: body.split_whitespace().to_vec().join(" "),
+            },
+        );
+    }
+
+    ///short preview of a SEND_STRING for a key legend
+    pub fn send_string_preview(&self, code: &str) -> Option<String> {
+        let text = self.send_strings.get(code)?;
+        let max = 8;
+        if text.chars().count() > max {
+            Some(format!(
+                "\"{}…\"",
+                text.chars().take(max - 1).collect::<String>()
+            ))
+        } else {
+            Some(format!("\"{text}\""))
+        }
+    }
+
+    fn add_send_string(&mut self, pair: Pair<Rule>) {
+        let mut inner = pair.into_inner();
+        let code = inner.next().unwrap().as_str().to_string();
+        let mut text = String::new();
+        for part in inner.next().unwrap().into_inner() {
+            match part.as_rule() {
+                Rule::string => text.push_str(&unescape(part.into_inner().as_str())),
+                //SS_TAP(X_ENTER) etc.
+                Rule::function if part.as_str().contains("X_ENT") => text.push('↵'),
+                Rule::function if part.as_str().contains("X_TAB") => text.push('⇥'),
+                Rule::function => text.push('…'),
+                _ => {}
+            }
+        }
+        self.send_strings.insert(code, text);
+    }
+
+    //only enums of keycodes, not layers etc.
+    fn add_enum(&mut self, pair: Pair<Rule>) {
+        let mut inner = pair.into_inner().peekable();
+        let name = match inner.peek() {
+            Some(p) if p.as_rule() == Rule::validname => inner.next().unwrap().as_str(),
+            _ => "",
+        };
+        let mut entries = vec![];
+        let mut is_keycodes = name.contains("keycodes");
+        for entry in inner {
+            let mut parts = entry.into_inner();
+            let code = parts.next().unwrap().as_str().to_string();
+            let mut label = None;
+            for part in parts {
+                match part.as_rule() {
+                    Rule::validname => {
+                        is_keycodes |= matches!(
+                            part.as_str(),
+                            "SAFE_RANGE" | "QK_USER" | "QK_KB" | "NEW_SAFE_RANGE"
+                        )
+                    }
+                    Rule::label => label = Some(part.into_inner().as_str().trim().to_string()),
+                    _ => {}
+                }
+            }
+            entries.push((code, label));
+        }
+        if !is_keycodes {
+            return;
+        }
+        for (code, label) in entries {
+            if let Some(label) = label {
+                self.labels.insert(code.clone(), label);
+            }
+            self.custom_keycodes.push(code);
+        }
+    }
+}
+
+//the arguments of a macro call starting at the open bracket, and where it ends
+fn call_args(chars: &[char], open: usize) -> Option<(Vec<String>, usize)> {
+    let mut depth = 0;
+    let mut args = vec![];
+    let mut arg = String::new();
+    for (i, c) in chars.iter().enumerate().skip(open) {
+        match c {
+            '(' => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    args.push(arg.trim().to_string());
+                    return Some((args, i + 1));
+                }
+            }
+            ',' if depth == 1 => {
+                args.push(arg.trim().to_string());
+                arg.clear();
+                continue;
+            }
+            _ => {}
+        }
+        arg.push(*c);
+    }
+    None
+}
+
+fn replace_idents(text: &str, f: impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::new();
+    let mut ident = String::new();
+    for c in text.chars().chain(std::iter::once(' ')) {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            ident.push(c);
+            continue;
+        }
+        if !ident.is_empty() {
+            result.push_str(&f(&ident).unwrap_or(ident.clone()));
+            ident.clear();
+        }
+        result.push(c);
+    }
+    result.pop();
+    result
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('↵'),
+            Some('t') => result.push('⇥'),
+            Some(c) => result.push(c),
+            None => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        key::{layer_switch, nice_code},
+        lint::is_known_keycode,
+    };
+
+    #[test]
+    fn tap_dances() {
+        let source = r#"
+        #include QMK_KEYBOARD_H
+        enum { TD_ESC_CAPS, TD_NUM };
+        // tap dances
+        tap_dance_action_t tap_dance_actions[] = {
+            [TD_ESC_CAPS] = ACTION_TAP_DANCE_DOUBLE(KC_ESC, KC_CAPS),
+            [TD_NUM] = ACTION_TAP_DANCE_LAYER_MOVE(KC_N, _NUM),
+        };
+        "#;
+        let ctx = Context::from_source(source);
+        assert_eq!(ctx.tap_dances.len(), 2);
+        let key = nice_code("TD(TD_ESC_CAPS)", &ctx);
+        assert_eq!(key.middle, "ESC");
+        assert_eq!(key.top, "2×⇪");
+        assert_eq!(
+            layer_switch("TD(TD_NUM)", &ctx),
+            Some(("TD TO".to_string(), "_NUM".to_string()))
+        );
+    }
+
+    #[test]
+    fn custom_keycodes() {
+        let source = r#"
+        enum layers { _BASE, _NUM };
+        enum custom_keycodes {
+            MY_COPY = SAFE_RANGE, // label: Copy
+            // not a label
+            MY_PASTE,  //label:Paste it
+            MY_OTHER // something else
+        };
+        "#;
+        let ctx = Context::from_source(source);
+        assert_eq!(ctx.custom_keycodes, vec!["MY_COPY", "MY_PASTE", "MY_OTHER"]);
+        assert_eq!(nice_code("MY_COPY", &ctx).middle, "Copy");
+        assert_eq!(nice_code("MY_PASTE", &ctx).middle, "Paste it");
+        assert_eq!(nice_code("MY_OTHER", &ctx).middle, "MY_OTHER");
+        assert!(is_known_keycode("MY_OTHER", &ctx));
+        assert!(!is_known_keycode("MY_OTHR", &ctx));
+        assert!(!is_known_keycode("_BASE", &ctx));
+    }
+
+    #[test]
+    fn send_strings() {
+        let source = r#"
+        bool process_record_user(uint16_t keycode, keyrecord_t *record) {
+            switch (keycode) {
+                case MY_HI:
+                    if (record->event.pressed) {
+                        SEND_STRING("hi");
+                    }
+                    break;
+                case MY_EMAIL:
+                    if (record->event.pressed) {
+                        SEND_STRING("me@example.com" SS_TAP(X_ENTER));
+                    }
+                    return false;
+                case MY_NOTHING:
+                    return false;
+                case MY_QUOTE: SEND_STRING("say \"hi\"\n"); break;
+            }
+            return true;
+        }
+        "#;
+        let ctx = Context::from_source(source);
+        assert_eq!(ctx.send_strings.len(), 3);
+        assert_eq!(ctx.send_strings["MY_EMAIL"], "me@example.com↵");
+        assert_eq!(ctx.send_strings["MY_QUOTE"], "say \"hi\"↵");
+        assert_eq!(nice_code("MY_HI", &ctx).middle, "\"hi\"");
+        assert_eq!(nice_code("MY_EMAIL", &ctx).middle, "\"me@exam…\"");
+    }
+
+    #[test]
+    fn defines() {
+        let source = r#"
+        #define _NAV 2
+        #define HOME_A LGUI_T(KC_A) // home row
+        #define HRM(m, k) m##_T(k)
+        #define NAV_SPC LT(_NAV, \
+                           KC_SPC)
+        #define MASK (1 << 2)
+        "#;
+        let ctx = Context::from_source(source);
+        assert_eq!(ctx.expand("HOME_A"), "LGUI_T(KC_A)");
+        assert_eq!(ctx.expand("HRM(LSFT, KC_S)"), "LSFT_T(KC_S)");
+        assert_eq!(ctx.expand("NAV_SPC"), "LT(2, KC_SPC)");
+        assert_eq!(ctx.expand("MASK"), "MASK");
+        assert_eq!(nice_code("HOME_A", &ctx).bottom, "⌘");
+        assert_eq!(nice_code("NAV_SPC", &ctx).bottom, "L-2");
+        assert_eq!(
+            layer_switch("NAV_SPC", &ctx),
+            Some(("LT".to_string(), "2".to_string()))
+        );
+        assert!(!is_known_keycode("MASK", &ctx));
+    }
+}
@@ -0,0 +1,194 @@
+use easier::prelude::*;
+
+use crate::{
+    key::{layer_switch, nice_code},
+    myparser::Keymap,
+    options::PrintOptions,
+};
+
+//layer switches that are held down, shown as held keys on the layer they switch to
+const HELD: &[&str] = &["MO", "LT", "LM", "TT", "OSL"];
+
+///keymap-drawer YAML, with the tap, hold and shifted legends from nice_code
+pub fn drawer_yaml(keymap: &Keymap, ops: &PrintOptions) -> String {
+    let active = keymap.active_layers();
+    let mut result = String::new();
+
+    result.push_str("layout:\n");
+    result.push_str(&ortho_layout(keymap, ops));
+
+    result.push_str("layers:\n");
+    for layer in &active {
+        //positions of the keys on other layers that are held to get to this one
+        let held = active
+            .iter()
+            .flat_map(|other| {
+                other
+                    .active_keys()
+                    .into_iter()
+                    .enumerate()
+                    .flat_map(|(ri, row)| {
+                        row.iter().enumerate().filter_map(move |(ci, code)| {
+                            let (how, to) = layer_switch(code.as_ref()?, &keymap.context)?;
+                            let to = keymap.context.expand(&to);
+                            let target = [layer.num.clone(), keymap.context.expand(&layer.num)];
+                            (HELD.contains(&how.as_str()) && target.contains(&to))
+                                .then_some((ri, ci))
+                        })
+                    })
+            })
+            .to_vec();
+        result.push_str(&format!("  {}:\n", layer.ident()));
+        for (ri, row) in layer.active_keys().into_iter().enumerate() {
+            let keys = row
+                .iter()
+                .enumerate()
+                .filter_map(|(ci, code)| {
+                    let code = code.as_ref()?;
+                    Some(drawer_key(code, keymap, held.contains(&(ri, ci))))
+                })
+                .to_vec();
+            result.push_str(&format!("    - [{}]\n", keys.join(", ")));
+        }
+    }
+
+    //combo positions are counted along the first layer
+    let Some(first) = active.first() else {
+        return result;
+    };
+    let positions = first.active_keys().into_iter().flatten().flatten().to_vec();
+    let combos = keymap
+        .context
+        .combos
+        .iter()
+        .filter_map(|combo| {
+            let p = combo
+                .keys
+                .iter()
+                .map(|key| positions.iter().position(|code| *code == key))
+                .collect::<Option<Vec<_>>>()?;
+            let p = p.iter().map(|i| i.to_string()).to_vec().join(", ");
+            let k = quote(&nice_code(&combo.result, &keymap.context).middle);
+            Some(format!("  - {{p: [{p}], k: {k}}}\n"))
+        })
+        .to_vec();
+    if !combos.is_empty() {
+        result.push_str("combos:\n");
+        for combo in combos {
+            result.push_str(&combo);
+        }
+    }
+    result
+}
+
+//e.g. "Q", or {t: "A", h: "⇧"} for a mod tap
+fn drawer_key(code: &str, keymap: &Keymap, held: bool) -> String {
+    let nice = nice_code(code, &keymap.context);
+    let expanded = keymap.context.expand(code);
+    let mut parts = vec![format!("t: {}", quote(&nice.middle))];
+    if !nice.bottom.is_empty() {
+        parts.push(format!("h: {}", quote(&nice.bottom)));
+    }
+    if !nice.top.is_empty() {
+        parts.push(format!("s: {}", quote(&nice.top)));
+    }
+    if held {
+        parts.push("type: held".to_string());
+    } else if expanded == "KC_TRNS" || expanded == "_______" {
+        parts.push("type: trans".to_string());
+    }
+    if parts.len() == 1 {
+        quote(&nice.middle)
+    } else {
+        format!("{{{}}}", parts.join(", "))
+    }
+}
+
+//the ortho layout keymap-drawer should draw, if every row has the same number of keys
+fn ortho_layout(keymap: &Keymap, ops: &PrintOptions) -> String {
+    let first = keymap.active_layers()[0];
+    let counts = first
+        .active_keys()
+        .iter()
+        .map(|row| row.iter().flatten().count())
+        .to_vec();
+    if !first.split {
+        return match counts.first() {
+            Some(columns) if counts.iter().all(|c| c == columns) => format!(
+                "  ortho_layout: {{split: false, rows: {}, columns: {columns}}}\n",
+                counts.len()
+            ),
+            _ => UNKNOWN_LAYOUT.to_string(),
+        };
+    }
+    let thumbs = ops.number_of_thumbs.min(counts.len());
+    let (rows, thumb_rows) = counts.split_at(counts.len() - thumbs);
+    let even = |c: &usize| c.is_multiple_of(2);
+    match (rows.first(), thumb_rows) {
+        (Some(columns), [thumbs])
+            if rows.iter().all(|c| c == columns && even(c)) && even(thumbs) =>
+        {
+            format!(
+                "  ortho_layout: {{split: true, rows: {}, columns: {}, thumbs: {}}}\n",
+                rows.len(),
+                columns / 2,
+                thumbs / 2
+            )
+        }
+        _ => UNKNOWN_LAYOUT.to_string(),
+    }
+}
+
+const UNKNOWN_LAYOUT: &str =
+    "  # set qmk_keyboard to your keyboard, the rows are not the same length\n  qmk_keyboard: \"\"\n";
+
+//always quoted, so legends like : or # are read as text
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        context::Context,
+        myparser::{get_keymap, MyParser, Rule},
+    };
+    use pest::Parser;
+
+    #[test]
+    fn keymap_drawer() {
+        let example = r#"
+            [0] = LAYOUT(KC_Q, KC_W, LSFT_T(KC_A), KC_S,
+                KC_1, MO(1)),
+            [1] = LAYOUT(KC_SCLN, KC_NO, KC_TRNS, KC_B,
+                KC_2, KC_TRNS),
+        "#;
+        let source = r#"
+            const uint16_t PROGMEM qw_combo[] = {KC_Q, KC_W, COMBO_END};
+            combo_t key_combos[] = { COMBO(qw_combo, KC_ESC) };
+        "#;
+        let ops = PrintOptions {
+            thumb_shift_in: 0,
+            ..Default::default()
+        };
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        let mut keymap = get_keymap(prog.next().unwrap(), &ops).unwrap();
+        keymap.context = Context::from_source(source);
+        assert_eq!(
+            drawer_yaml(&keymap, &ops),
+            r#"layout:
+  ortho_layout: {split: true, rows: 1, columns: 2, thumbs: 1}
+layers:
+  layer_0:
+    - ["Q", "W", {t: "A", h: "⇧"}, "S"]
+    - [{t: "1", s: "!"}, "MO(1)"]
+  layer_1:
+    - [{t: ";", s: ":"}, "", {t: "⇄", type: trans}, "B"]
+    - [{t: "2", s: "@"}, {t: "⇄", type: held}]
+combos:
+  - {p: [0, 1], k: "ESC"}
+"#
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use svg::{
-    node::element::{Group, Rectangle, Text},
+    node::element::{Circle, Group, Rectangle, Text},
     Document,
 };
 
@@ -19,16 +19,26 @@ pub fn create_svg(keymap: &Keymap, ops: &PrintOptions) -> String {
     let key_width = 100;
     let key_height = 70;
     let padding = 50;
-    let centre_width = ops.split_space * 10;
-    let full_width = keymap.layers.first().unwrap().keys.first().unwrap().len() * key_width
-        + padding * 2
-        + centre_width;
-    let layer_height = keymap.layers.first().unwrap().keys.len() * key_height + padding * 2;
-    let full_height = layer_height * keymap.layers.len();
+    //boards in one piece have no gap
+    let centre_width = if keymap.layers[0].split {
+        ops.split_space * 10
+    } else {
+        0
+    };
+    let encoder_width = 220;
+    //only what is compiled with the defined symbols
+    let layers = keymap.active_layers();
+    let first = layers.first().unwrap().active_keys();
+    let grid_width = first.first().unwrap().len() * key_width + padding + centre_width;
+    let has_encoders = layers.iter().any(|l| !l.encoders.is_empty());
+    let full_width = grid_width + padding + if has_encoders { encoder_width } else { 0 };
+    let max_encoders = layers.iter().map(|l| l.encoders.len()).max().unwrap();
+    let layer_height = first.len().max(max_encoders) * key_height + padding * 2;
+    let full_height = layer_height * layers.len();
     let mut groups = vec![];
 
-    for (layi, layer) in keymap.layers.iter().enumerate() {
-        let grid = &layer.keys;
+    for (layi, layer) in layers.iter().enumerate() {
+        let grid = layer.active_keys();
         let mut group = Group::new();
         let layer_name = if layer.num.len() == 1 {
             format!("Layer {}", layer.num)
@@ -74,7 +84,7 @@ pub fn create_svg(keymap: &Keymap, ops: &PrintOptions) -> String {
                         .set("stroke-width", 2);
 
                     group = group.add(rect);
-                    let nice = nice_code(code);
+                    let nice = nice_code(code, &keymap.context);
                     let text_middle = Text::new(nice.middle)
                         .set("x", x + key_width / 2)
                         .set("y", y + key_height / 2)
@@ -102,6 +112,38 @@ pub fn create_svg(keymap: &Keymap, ops: &PrintOptions) -> String {
                 }
             }
         }
+        //encoders are drawn as knobs to the right of the layer
+        for (ei, encoder) in layer.encoders.iter().enumerate() {
+            let cx = grid_width + encoder_width / 2;
+            let cy = layi * layer_height + ei * key_height + padding + key_height / 2 - 3;
+            let knob = Circle::new()
+                .set("cx", cx)
+                .set("cy", cy)
+                .set("r", 25)
+                .set("fill", "#2c2c2c")
+                .set("stroke", "#555555")
+                .set("stroke-width", 2);
+            group = group.add(knob);
+            for (label, anchor, dx, arrow) in [
+                (&encoder.ccw, "end", -35, "↺"),
+                (&encoder.cw, "start", 35, "↻"),
+            ] {
+                let nice = nice_code(label, &keymap.context).middle;
+                let text = if anchor == "end" {
+                    format!("{nice} {arrow}")
+                } else {
+                    format!("{arrow} {nice}")
+                };
+                let text = Text::new(text)
+                    .set("x", cx as i32 + dx)
+                    .set("y", cy)
+                    .set("fill", "white")
+                    .set("stroke", "none")
+                    .set("text-anchor", anchor)
+                    .set("dominant-baseline", "middle");
+                group = group.add(text);
+            }
+        }
         groups.push(group);
     }
     let mut doc = Document::new().set("viewBox", (0, 0, full_width, full_height));
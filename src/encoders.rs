@@ -0,0 +1,151 @@
+use easier::prelude::*;
+use pest::iterators::Pair;
+
+use crate::{
+    error::MyError,
+    myparser::{format_pair, Keymap, Rule},
+    options::PrintOptions,
+};
+
+#[derive(Clone)]
+pub struct Encoder {
+    pub ccw: String,
+    pub cw: String,
+}
+
+///adds the encoders from the encoder_map contents to the matching layers
+pub fn add_encoders(keymap: &mut Keymap, pair: Pair<Rule>) -> Result<(), MyError> {
+    assert!(pair.as_rule() == Rule::encodermap);
+    for block in pair.into_inner() {
+        let mut inner = block.into_inner();
+        let num = inner.next().unwrap().as_str().to_string();
+        let encoders = inner
+            .map(|encoder| {
+                let mut keys = encoder.into_inner();
+                Encoder {
+                    ccw: format_pair(keys.next().unwrap()),
+                    cw: format_pair(keys.next().unwrap()),
+                }
+            })
+            .to_vec();
+        //a layer may be in more than one #if branch
+        let layers = keymap.layers.iter_mut().filter(|l| l.num == num).to_vec();
+        if layers.is_empty() {
+            return Err(format!("Encoder layer {num} is not in the keymap").into());
+        }
+        for layer in layers {
+            layer.encoders = encoders.clone();
+        }
+    }
+    Ok(())
+}
+
+///encoder_map contents, with each encoder aligned in columns across layers
+pub fn encoder_string(keymap: &Keymap, ops: &PrintOptions) -> String {
+    let layers = keymap
+        .layers
+        .iter()
+        .filter(|l| !l.encoders.is_empty())
+        .to_vec();
+    let count = layers
+        .iter()
+        .map(|l| l.encoders.len())
+        .max()
+        .unwrap_or_default();
+    let ccw_widths = (0..count)
+        .map(|i| {
+            layers
+                .iter()
+                .filter_map(|l| l.encoders.get(i))
+                .map(|e| e.ccw.len())
+                .max()
+                .unwrap_or_default()
+        })
+        .to_vec();
+    let items = layers
+        .iter()
+        .map(|l| {
+            l.encoders
+                .iter()
+                .enumerate()
+                .map(|(i, e)| {
+                    let comma = if i < l.encoders.len() - 1 { "," } else { "" };
+                    if ops.align_layers {
+                        let ccw = format!("{},", e.ccw);
+                        let width = ccw_widths[i] + 1;
+                        format!("ENCODER_CCW_CW({ccw: <width$} {}){comma}", e.cw)
+                    } else {
+                        format!("ENCODER_CCW_CW({}, {}){comma}", e.ccw, e.cw)
+                    }
+                })
+                .to_vec()
+        })
+        .to_vec();
+    let item_widths = (0..count)
+        .map(|i| {
+            items
+                .iter()
+                .filter_map(|l| l.get(i))
+                .map(|e| e.len())
+                .max()
+                .unwrap_or_default()
+        })
+        .to_vec();
+    let num_width = layers.iter().map(|l| l.num.len()).max().unwrap_or_default();
+
+    let mut result = String::new();
+    for (layer, items) in layers.iter().zip(items) {
+        let encoders = items
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                if ops.align_layers {
+                    let width = item_widths[i];
+                    format!("{e: <width$}")
+                } else {
+                    e.clone()
+                }
+            })
+            .to_vec()
+            .join(" ");
+        let num = if ops.align_layers {
+            format!("[{}]{}", layer.num, " ".repeat(num_width - layer.num.len()))
+        } else {
+            format!("[{}]", layer.num)
+        };
+        result.push_str(&format!("{num} = {{ {encoders} }},\n"));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use pest::Parser;
+
+    use super::*;
+    use crate::myparser::{get_keymap, MyParser};
+
+    #[test]
+    fn encoders_by_layer() {
+        let example = r#"
+            [0] = LAYOUT( KC_A, KC_B ),
+            [_RAISE] = LAYOUT( KC_1, KC_2 )
+        "#;
+        let encoder_map = r#"
+            [_RAISE]={ENCODER_CCW_CW(KC_LEFT,KC_RIGHT), ENCODER_CCW_CW(KC_A, KC_B)},
+            [0] = { ENCODER_CCW_CW(KC_VOLD, KC_VOLU), ENCODER_CCW_CW(KC_PGDN, KC_PGUP) }
+        "#;
+        let ops = PrintOptions::default();
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        let mut keymap = get_keymap(prog.next().unwrap(), &ops).unwrap();
+        let mut encoders = MyParser::parse(Rule::encodermapouter, encoder_map).unwrap();
+        add_encoders(&mut keymap, encoders.next().unwrap()).unwrap();
+        assert_eq!(keymap.layers[1].encoders[0].cw, "KC_RIGHT");
+        assert_eq!(
+            encoder_string(&keymap, &ops),
+            r#"[0]      = { ENCODER_CCW_CW(KC_VOLD, KC_VOLU),  ENCODER_CCW_CW(KC_PGDN, KC_PGUP) },
+[_RAISE] = { ENCODER_CCW_CW(KC_LEFT, KC_RIGHT), ENCODER_CCW_CW(KC_A,    KC_B)    },
+"#
+        );
+    }
+}
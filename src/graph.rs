@@ -0,0 +1,53 @@
+use easier::prelude::*;
+
+use crate::{key::layer_switch, myparser::Keymap};
+
+/// A key on one layer that switches to another layer
+#[derive(PartialEq)]
+pub struct LayerLink {
+    pub from: String,
+    pub to: String,
+    pub how: String,
+}
+
+pub fn layer_links(keymap: &Keymap) -> Vec<LayerLink> {
+    let mut links = vec![];
+    for layer in keymap.active_layers() {
+        for code in layer.active_keys().into_iter().flatten().flatten() {
+            let Some((how, to)) = layer_switch(code, &keymap.context) else {
+                continue;
+            };
+            //the target may have been expanded from a #define, so match it to the layer's own name
+            let to = keymap
+                .layers
+                .iter()
+                .find(|l| keymap.context.expand(&l.num) == keymap.context.expand(&to))
+                .map(|l| l.num.clone())
+                .unwrap_or(to);
+            let link = LayerLink {
+                from: layer.num.clone(),
+                to,
+                how,
+            };
+            if !links.contains(&link) {
+                links.push(link);
+            }
+        }
+    }
+    links
+}
+
+///graphviz dot of how the layers are reached
+pub fn graph_dot(keymap: &Keymap) -> String {
+    let edges = layer_links(keymap)
+        .iter()
+        .map(|l| {
+            format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                l.from, l.to, l.how
+            )
+        })
+        .to_vec()
+        .join("");
+    format!("digraph layers {{\n{edges}}}\n")
+}
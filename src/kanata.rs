@@ -0,0 +1,411 @@
+use easier::prelude::*;
+
+use crate::{
+    error::MyError,
+    key::{short_keycode, split_call},
+    myparser::{layers_string, Keymap, Layer},
+    options::PrintOptions,
+    preprocessor::Item,
+};
+
+//QMK's default tapping term, in ms
+const TAPPING_TERM: u32 = 200;
+
+//QMK keycodes and their name in Kanata and KMonad, letters, numbers and F keys are worked out in code
+const KEYS: &[(&str, &str)] = &[
+    ("KC_TRNS", "_"),
+    ("KC_NO", "XX"),
+    ("KC_ENT", "ret"),
+    ("KC_ESC", "esc"),
+    ("KC_BSPC", "bspc"),
+    ("KC_TAB", "tab"),
+    ("KC_SPC", "spc"),
+    ("KC_MINS", "-"),
+    ("KC_EQL", "="),
+    ("KC_LBRC", "["),
+    ("KC_RBRC", "]"),
+    ("KC_BSLS", "\\"),
+    ("KC_SCLN", ";"),
+    ("KC_QUOT", "'"),
+    ("KC_GRV", "grv"),
+    ("KC_COMM", ","),
+    ("KC_DOT", "."),
+    ("KC_SLSH", "/"),
+    ("KC_CAPS", "caps"),
+    ("KC_PSCR", "prnt"),
+    ("KC_INS", "ins"),
+    ("KC_HOME", "home"),
+    ("KC_PGUP", "pgup"),
+    ("KC_DEL", "del"),
+    ("KC_END", "end"),
+    ("KC_PGDN", "pgdn"),
+    ("KC_RGHT", "right"),
+    ("KC_LEFT", "left"),
+    ("KC_DOWN", "down"),
+    ("KC_UP", "up"),
+    ("KC_APP", "menu"),
+    ("KC_LCTL", "lctl"),
+    ("KC_LSFT", "lsft"),
+    ("KC_LALT", "lalt"),
+    ("KC_LGUI", "lmet"),
+    ("KC_RCTL", "rctl"),
+    ("KC_RSFT", "rsft"),
+    ("KC_RALT", "ralt"),
+    ("KC_RGUI", "rmet"),
+    ("KC_MUTE", "mute"),
+    ("KC_VOLU", "volu"),
+    ("KC_VOLD", "vold"),
+    ("KC_MPLY", "pp"),
+    ("KC_MNXT", "next"),
+    ("KC_MPRV", "prev"),
+    //shifted symbols
+    ("KC_EXLM", "S-1"),
+    ("KC_AT", "S-2"),
+    ("KC_HASH", "S-3"),
+    ("KC_DLR", "S-4"),
+    ("KC_PERC", "S-5"),
+    ("KC_CIRC", "S-6"),
+    ("KC_AMPR", "S-7"),
+    ("KC_ASTR", "S-8"),
+    ("KC_LPRN", "S-9"),
+    ("KC_RPRN", "S-0"),
+    ("KC_UNDS", "S--"),
+    ("KC_PLUS", "S-="),
+    ("KC_LCBR", "S-["),
+    ("KC_RCBR", "S-]"),
+    ("KC_PIPE", "S-\\"),
+    ("KC_COLN", "S-;"),
+    ("KC_DQUO", "S-'"),
+    ("KC_TILD", "S-grv"),
+    ("KC_LT", "S-,"),
+    ("KC_GT", "S-."),
+    ("KC_QUES", "S-/"),
+];
+
+//modifier functions e.g. LSFT(KC_1) is S-1
+const MOD_PREFIXES: &[(&str, &str)] = &[
+    ("S", "S-"),
+    ("LSFT", "S-"),
+    ("RSFT", "S-"),
+    ("C", "C-"),
+    ("LCTL", "C-"),
+    ("RCTL", "C-"),
+    ("A", "A-"),
+    ("LALT", "A-"),
+    ("RALT", "A-"),
+    ("G", "M-"),
+    ("LGUI", "M-"),
+    ("RGUI", "M-"),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Kanata,
+    KMonad,
+}
+
+///defsrc, defalias and deflayer blocks for the active layers of a keymap, in aligned columns,
+///and a warning for each key that can't be exported
+pub fn kanata_string(
+    keymap: &Keymap,
+    ops: &PrintOptions,
+    dialect: Dialect,
+) -> Result<(String, Vec<String>), MyError> {
+    let active = keymap.active_layers();
+    let mut warnings = vec![];
+    let mut aliases: Vec<(String, String)> = vec![];
+    let layer_rows = active
+        .iter()
+        .map(|layer| {
+            layer
+                .active_keys()
+                .into_iter()
+                .map(|row| {
+                    row.iter()
+                        .flatten()
+                        .map(|code| {
+                            let expanded = keymap.context.expand(code);
+                            match action(&expanded, &active, dialect) {
+                                Some((name, Some(alias))) => {
+                                    if !aliases.iter().any(|(n, _)| *n == name) {
+                                        aliases.push((name.clone(), alias));
+                                    }
+                                    format!("@{name}")
+                                }
+                                Some((name, None)) => name,
+                                None => {
+                                    warnings.push(format!(
+                                        "Layer {}: no equivalent for {code}",
+                                        layer.num
+                                    ));
+                                    "XX".to_string()
+                                }
+                            }
+                        })
+                        .to_vec()
+                })
+                .to_vec()
+        })
+        .to_vec();
+
+    //the physical keys are the taps on the first layer
+    let Some(first) = active.first() else {
+        return Err("No layers are active with these defines".to_string().into());
+    };
+    let mut sources = vec![];
+    let src_rows = first
+        .active_keys()
+        .into_iter()
+        .map(|row| {
+            row.iter()
+                .flatten()
+                .map(|code| {
+                    let tap = tap_key(&keymap.context.expand(code));
+                    match tap {
+                        Some(tap) if tap != "_" && tap != "XX" && !sources.contains(&tap) => {
+                            sources.push(tap.clone());
+                            Some(tap)
+                        }
+                        _ => {
+                            warnings.push(format!("No source key for {code}, column left out"));
+                            None
+                        }
+                    }
+                })
+                .to_vec()
+        })
+        .to_vec();
+
+    //leave out keys without a source key from every layer
+    let keep = |ri: usize, ki: usize| src_rows[ri][ki].is_some();
+    let mut layers = vec![];
+    let src = src_rows
+        .iter()
+        .map(|row| Item::Item(row.iter().flatten().cloned().to_vec()))
+        .to_vec();
+    //the deflayer names aren't LAYOUT names, so each keeps the split of its QMK layer
+    let split = active.first().is_some_and(|l| l.split);
+    layers.push(Item::Item(Layer::new(
+        "src".to_string(),
+        "defsrc".to_string(),
+        src,
+        split,
+        ops,
+    )?));
+    for (layer, rows) in active.iter().zip(layer_rows) {
+        let rows = rows
+            .into_iter()
+            .enumerate()
+            .map(|(ri, row)| {
+                let row = row
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(ki, _)| keep(ri, *ki))
+                    .map(|(_, a)| a)
+                    .to_vec();
+                Item::Item(row)
+            })
+            .to_vec();
+        layers.push(Item::Item(Layer::new(
+            layer.num.clone(),
+            format!("deflayer {}", layer.ident()),
+            rows,
+            layer.split,
+            ops,
+        )?));
+    }
+    let exported = Keymap::new(layers, ops)?;
+    let layouts = layers_string(&exported, ops, " ");
+
+    let mut result = String::new();
+    for (i, (layer, layout)) in exported.layers.iter().zip(layouts).enumerate() {
+        result.push_str(&format!("({}\n", layer.name()));
+        for line in layout.lines() {
+            result.push_str(&format!("  {}\n", line.trim_end()));
+        }
+        result.push_str(")\n\n");
+        if i == 0 && !aliases.is_empty() {
+            result.push_str("(defalias\n");
+            for (name, alias) in &aliases {
+                result.push_str(&format!("  {name} {alias}\n"));
+            }
+            result.push_str(")\n\n");
+        }
+    }
+    Ok((result, warnings))
+}
+
+//the key in a layer, and an alias for it if it needs one
+fn action(code: &str, layers: &[&Layer], dialect: Dialect) -> Option<(String, Option<String>)> {
+    let Some((name, params)) = split_call(code) else {
+        return Some((key_name(code)?, None));
+    };
+    let layer = |l: &String| match layers.iter().find(|layer| layer.num == *l) {
+        Some(layer) => layer.ident(),
+        None => l.to_lowercase(),
+    };
+    let (held, switch, tap_hold, one_shot) = match dialect {
+        Dialect::Kanata => (
+            "layer-while-held",
+            "layer-switch",
+            format!("tap-hold {TAPPING_TERM} {TAPPING_TERM}"),
+            "one-shot",
+        ),
+        Dialect::KMonad => (
+            "layer-toggle",
+            "layer-switch",
+            format!("tap-hold-next-release {TAPPING_TERM}"),
+            "sticky-key",
+        ),
+    };
+    let alias = match (name.as_str(), params.as_slice()) {
+        ("MO", [l]) => (format!("mo_{}", layer(l)), format!("({held} {})", layer(l))),
+        ("TO" | "DF", [l]) => (
+            format!("to_{}", layer(l)),
+            format!("({switch} {})", layer(l)),
+        ),
+        ("LT", [l, key]) => {
+            let tap = key_name(key)?;
+            (
+                format!("{}_{}", alias_part(key), layer(l)),
+                format!("({tap_hold} {tap} ({held} {}))", layer(l)),
+            )
+        }
+        ("OSM", [modifier]) => {
+            let modifier = modifier_key(modifier.strip_prefix("MOD_")?)?;
+            (
+                format!("os_{modifier}"),
+                format!("({one_shot} {TAPPING_TERM} {modifier})"),
+            )
+        }
+        ("MT", [modifier, key]) => mod_tap(modifier.strip_prefix("MOD_")?, key, &tap_hold)?,
+        (name, [key]) if name.ends_with("_T") => mod_tap(name.strip_suffix("_T")?, key, &tap_hold)?,
+        _ => return Some((key_name(code)?, None)),
+    };
+    Some((alias.0, Some(alias.1)))
+}
+
+//e.g. LSFT and KC_A to (a_lsft, tap-hold of a and lsft)
+fn mod_tap(modifier: &str, key: &str, tap_hold: &str) -> Option<(String, String)> {
+    let modifier = modifier_key(modifier)?;
+    let tap = key_name(key)?;
+    Some((
+        format!("{}_{modifier}", alias_part(key)),
+        format!("({tap_hold} {tap} {modifier})"),
+    ))
+}
+
+//e.g. SFT to lsft
+fn modifier_key(modifier: &str) -> Option<String> {
+    let name = key_name(&format!("KC_{modifier}"))?;
+    [
+        "lsft", "lctl", "lalt", "lmet", "rsft", "rctl", "ralt", "rmet",
+    ]
+    .contains(&name.as_str())
+    .then_some(name)
+}
+
+//alias names can't have symbols in them, so we use the QMK name e.g. KC_SCLN to scln
+fn alias_part(code: &str) -> String {
+    let code = short_keycode(code);
+    code.strip_prefix("KC_").unwrap_or(code).to_lowercase()
+}
+
+//the key that is typed when tapped, used for defsrc
+fn tap_key(code: &str) -> Option<String> {
+    match split_call(code) {
+        Some((name, params)) => match (name.as_str(), params.as_slice()) {
+            ("LT" | "MT", [_, key]) => key_name(key),
+            (name, [key]) if name.ends_with("_T") => key_name(key),
+            _ => None,
+        },
+        None => key_name(code),
+    }
+}
+
+//e.g. KC_A to a, KC_SCLN to ;, LSFT(KC_1) to S-1
+fn key_name(code: &str) -> Option<String> {
+    let code = short_keycode(code);
+    if let Some((name, params)) = split_call(code) {
+        let (_, prefix) = MOD_PREFIXES.iter().find(|(m, _)| *m == name)?;
+        return match params.as_slice() {
+            [key] => Some(format!("{prefix}{}", key_name(key)?)),
+            _ => None,
+        };
+    }
+    if let Some((_, name)) = KEYS.iter().find(|(qmk, _)| *qmk == code) {
+        return Some(name.to_string());
+    }
+    let rest = code.strip_prefix("KC_")?;
+    let function = rest
+        .strip_prefix('F')
+        .and_then(|n| n.parse::<u8>().ok())
+        .is_some_and(|n| (1..=24).contains(&n));
+    if rest.len() == 1 && rest.chars().all(|c| c.is_ascii_alphanumeric()) || function {
+        Some(rest.to_lowercase())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::myparser::{get_keymap, MyParser, Rule};
+    use pest::Parser;
+
+    #[test]
+    fn kanata() {
+        let example = r#"
+            [_BASE] = LAYOUT(KC_Q, LSFT_T(KC_A), LT(_NAV, KC_SPC), KC_SCLN),
+            [_NAV] = LAYOUT(KC_1, S(KC_1), _______, RGB_TOG),
+        "#;
+        let ops = PrintOptions {
+            thumb_shift_in: 0,
+            ..Default::default()
+        };
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        let keymap = get_keymap(prog.next().unwrap(), &ops).unwrap();
+        let (out, warnings) = kanata_string(&keymap, &ops, Dialect::Kanata).unwrap();
+        assert_eq!(
+            out,
+            r#"(defsrc
+   q        a      spc       ;
+)
+
+(defalias
+  a_lsft (tap-hold 200 200 a lsft)
+  spc_nav (tap-hold 200 200 spc (layer-while-held nav))
+)
+
+(deflayer base
+   q  @a_lsft      @spc_nav  ;
+)
+
+(deflayer nav
+   1      S-1      _         XX
+)
+
+"#
+        );
+        assert_eq!(warnings, vec!["Layer _NAV: no equivalent for RGB_TOG"]);
+        let (out, _) = kanata_string(&keymap, &ops, Dialect::KMonad).unwrap();
+        assert!(out.contains("a_lsft (tap-hold-next-release 200 a lsft)"));
+    }
+
+    #[test]
+    fn unsplit_board() {
+        let example = r#"
+            [0] = LAYOUT_ortho_2x4(KC_Q, KC_W, KC_E, KC_R,
+                KC_A, KC_S, KC_D, KC_F),
+        "#;
+        let ops = PrintOptions::default();
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        let keymap = get_keymap(prog.next().unwrap(), &ops).unwrap();
+        let (out, _) = kanata_string(&keymap, &ops, Dialect::Kanata).unwrap();
+        assert_eq!(
+            out,
+            "(defsrc\n  q  w  e  r\n  a  s  d  f\n)\n\n(deflayer layer_0\n  q  w  e  r\n  a  s  d  f\n)\n\n"
+        );
+    }
+}
@@ -1,7 +1,11 @@
 use easier::prelude::*;
 use pest::Parser;
 
-use crate::{myparser::format_pair, MyParser, Rule};
+use crate::{
+    context::{Context, TapDance},
+    myparser::format_pair,
+    MyParser, Rule,
+};
 
 #[derive(Default)]
 pub struct PrintKey {
@@ -33,8 +37,11 @@ enum KeyType {
 }
 impl From<&str> for KeyType {
     fn from(value: &str) -> Self {
-        let parsed = MyParser::parse(Rule::key, value)
-            .unwrap()
+        let Ok(mut pairs) = MyParser::parse(Rule::key, value) else {
+            //e.g. the 4 in LT(1, 4), kept as it is
+            return KeyType::KeyCode(value.to_string());
+        };
+        let parsed = pairs
             .next() //from multiple to 1
             .unwrap()
             .into_inner() //go to keycode or function
@@ -62,82 +69,243 @@ impl From<&str> for KeyType {
     }
 }
 
-pub fn nice_code(code: &str) -> PrintKey {
-    let t: KeyType = code.into();
+pub fn nice_code(code: &str, ctx: &Context) -> PrintKey {
+    let code = ctx.expand(code);
+    //ZMK behaviours with no QMK equivalent, e.g. &bt BT_CLR
+    if let Some(binding) = code.strip_prefix('&') {
+        return match binding.split_once(' ') {
+            Some((behaviour, params)) => PrintKey::mt(params, behaviour),
+            None => PrintKey::new(binding),
+        };
+    }
+    let t: KeyType = code.as_str().into();
 
     match t {
+        KeyType::KeyCode(code) if ctx.labels.contains_key(&code) => {
+            PrintKey::new(&ctx.labels[&code])
+        }
+        KeyType::KeyCode(code) if ctx.send_strings.contains_key(&code) => {
+            PrintKey::new(&ctx.send_string_preview(&code).unwrap())
+        }
         KeyType::KeyCode(code) => nice_keycode(&code),
-        KeyType::Function(name, params, raw) => nice_function(&name, &params, &raw),
+        KeyType::Function(name, params, raw) => nice_function(&name, &params, &raw, ctx),
     }
 }
 
-fn nice_function(name: &str, params: &[String], raw: &str) -> PrintKey {
-    if name == "LT" && params.len() == 2 {
-        let tap = nice_code(&params[1]);
+fn nice_function(name: &str, params: &[String], raw: &str, ctx: &Context) -> PrintKey {
+    if name == "TD" && params.len() == 1 && ctx.tap_dances.contains_key(&params[0]) {
+        nice_tap_dance(&ctx.tap_dances[&params[0]], ctx)
+    } else if name == "LT" && params.len() == 2 {
+        let tap = nice_code(&params[1], ctx);
         PrintKey {
             top: tap.top,
             middle: tap.middle,
             bottom: format!("L-{}", params[0]),
         }
     } else if (name == "LSFT_T" || name == "RSFT_T" || name == "SFT_T") && params.len() == 1 {
-        let tap = nice_code(&params[0]);
+        let tap = nice_code(&params[0], ctx);
         PrintKey {
             top: tap.top,
             middle: tap.middle,
             bottom: "⇧".to_string(),
         }
     } else if (name == "LCTL_T" || name == "RCTL_T" || name == "CTL_T") && params.len() == 1 {
-        let tap = nice_code(&params[0]);
+        let tap = nice_code(&params[0], ctx);
         PrintKey {
             top: tap.top,
             middle: tap.middle,
             bottom: "⌃".to_string(),
         }
     } else if (name == "LALT_T" || name == "RALT_T" || name == "ALT_T") && params.len() == 1 {
-        let tap = nice_code(&params[0]);
+        let tap = nice_code(&params[0], ctx);
         PrintKey {
             top: tap.top,
             middle: tap.middle,
             bottom: "⌥".to_string(),
         }
     } else if (name == "LGUI_T" || name == "RGUI_T" || name == "GUI_T") && params.len() == 1 {
-        let tap = nice_code(&params[0]);
+        let tap = nice_code(&params[0], ctx);
         PrintKey {
             top: tap.top,
             middle: tap.middle,
             bottom: "⌘".to_string(),
         }
     } else if (name == "MEH_T") && params.len() == 1 {
-        let tap = nice_code(&params[0]);
+        let tap = nice_code(&params[0], ctx);
         PrintKey {
             top: tap.top,
             middle: tap.middle,
             bottom: "MEH".to_string(),
         }
     } else if (name == "HYPR" || name == "ALL_T") && params.len() == 1 {
-        let tap = nice_code(&params[0]);
+        let tap = nice_code(&params[0], ctx);
         PrintKey {
             top: tap.top,
             middle: tap.middle,
             bottom: "HYPR".to_string(),
         }
     } else if (name == "S" || name == "LSFT") && params.len() == 1 {
-        let tap = nice_code(&params[0]);
+        let tap = nice_code(&params[0], ctx);
         PrintKey::mt(&format!("⇧{}", tap.middle), &tap.top)
     } else if (name == "C" || name == "LCTL") && params.len() == 1 {
-        let tap = nice_code(&params[0]);
+        let tap = nice_code(&params[0], ctx);
         PrintKey::mt(&format!("⌃{}", tap.middle), &tap.top)
     } else if (name == "A" || name == "LALT") && params.len() == 1 {
-        let tap = nice_code(&params[0]);
+        let tap = nice_code(&params[0], ctx);
         PrintKey::mt(&format!("⌥{}", tap.middle), &tap.top)
     } else if (name == "G" || name == "LGUI") && params.len() == 1 {
-        let tap = nice_code(&params[0]);
+        let tap = nice_code(&params[0], ctx);
         PrintKey::mt(&format!("⌘{}", tap.middle), &tap.top)
     } else {
         PrintKey::new(raw)
     }
 }
 
+///the layer a key switches to, and how, e.g. ("MO", "1")
+pub fn layer_switch(code: &str, ctx: &Context) -> Option<(String, String)> {
+    let code = ctx.expand(code);
+    if code.starts_with('&') {
+        return None;
+    }
+    let KeyType::Function(name, params, _) = code.as_str().into() else {
+        return None;
+    };
+    match (name.as_str(), params.as_slice()) {
+        ("MO" | "TG" | "TO" | "DF" | "PDF" | "OSL" | "TT", [layer]) => {
+            Some((name.clone(), layer.clone()))
+        }
+        ("LT" | "LM", [layer, _]) => Some((name.clone(), layer.clone())),
+        ("TD", [dance]) => match ctx.tap_dances.get(dance) {
+            Some(TapDance::LayerMove(_, layer)) => Some(("TD TO".to_string(), layer.clone())),
+            Some(TapDance::LayerToggle(_, layer)) => Some(("TD TG".to_string(), layer.clone())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+///name and parameters of a function key, e.g. LT(1,KC_A) to ("LT", ["1", "KC_A"])
+pub fn split_call(code: &str) -> Option<(String, Vec<String>)> {
+    let key = MyParser::parse(Rule::key, code).ok()?.next()?;
+    let function = key.into_inner().next()?;
+    if function.as_rule() != Rule::function {
+        return None;
+    }
+    let mut inner = function.into_inner();
+    let name = inner.next()?.as_str().to_string();
+    let params = inner
+        .next()?
+        .into_inner()
+        .map(|p| p.as_str().trim().to_string())
+        .to_vec();
+    Some((name, params))
+}
+
+//single tap in the middle, double tap on top
+fn nice_tap_dance(dance: &TapDance, ctx: &Context) -> PrintKey {
+    let (tap, double) = match dance {
+        TapDance::Double(tap, double) => (tap, nice_code(double, ctx).middle),
+        TapDance::LayerMove(tap, layer) => (tap, format!("TO-{layer}")),
+        TapDance::LayerToggle(tap, layer) => (tap, format!("TG-{layer}")),
+        TapDance::Other(name) => return PrintKey::mt("TD", name),
+    };
+    let tap = nice_code(tap, ctx);
+    PrintKey {
+        top: format!("2×{double}"),
+        middle: tap.middle,
+        bottom: "TD".to_string(),
+    }
+}
+
+//long QMK names and their short name
+const QMK_ALIASES: &[(&str, &str)] = &[
+    ("KC_ENTER", "KC_ENT"),
+    ("KC_ESCAPE", "KC_ESC"),
+    ("KC_BACKSPACE", "KC_BSPC"),
+    ("KC_SPACE", "KC_SPC"),
+    ("KC_MINUS", "KC_MINS"),
+    ("KC_EQUAL", "KC_EQL"),
+    ("KC_LEFT_BRACKET", "KC_LBRC"),
+    ("KC_RIGHT_BRACKET", "KC_RBRC"),
+    ("KC_BACKSLASH", "KC_BSLS"),
+    ("KC_SEMICOLON", "KC_SCLN"),
+    ("KC_QUOTE", "KC_QUOT"),
+    ("KC_GRAVE", "KC_GRV"),
+    ("KC_COMMA", "KC_COMM"),
+    ("KC_SLASH", "KC_SLSH"),
+    ("KC_CAPS_LOCK", "KC_CAPS"),
+    ("KC_PRINT_SCREEN", "KC_PSCR"),
+    ("KC_SCROLL_LOCK", "KC_SCRL"),
+    ("KC_PAUSE", "KC_PAUS"),
+    ("KC_INSERT", "KC_INS"),
+    ("KC_PAGE_UP", "KC_PGUP"),
+    ("KC_PAGE_DOWN", "KC_PGDN"),
+    ("KC_DELETE", "KC_DEL"),
+    ("KC_RIGHT", "KC_RGHT"),
+    ("KC_APPLICATION", "KC_APP"),
+    ("KC_LEFT_CTRL", "KC_LCTL"),
+    ("KC_LEFT_SHIFT", "KC_LSFT"),
+    ("KC_LEFT_ALT", "KC_LALT"),
+    ("KC_LOPT", "KC_LALT"),
+    ("KC_LEFT_GUI", "KC_LGUI"),
+    ("KC_LCMD", "KC_LGUI"),
+    ("KC_LWIN", "KC_LGUI"),
+    ("KC_RIGHT_CTRL", "KC_RCTL"),
+    ("KC_RIGHT_SHIFT", "KC_RSFT"),
+    ("KC_RIGHT_ALT", "KC_RALT"),
+    ("KC_ROPT", "KC_RALT"),
+    ("KC_ALGR", "KC_RALT"),
+    ("KC_RIGHT_GUI", "KC_RGUI"),
+    ("KC_RCMD", "KC_RGUI"),
+    ("KC_RWIN", "KC_RGUI"),
+    ("KC_EXCLAIM", "KC_EXLM"),
+    ("KC_DOLLAR", "KC_DLR"),
+    ("KC_PERCENT", "KC_PERC"),
+    ("KC_CIRCUMFLEX", "KC_CIRC"),
+    ("KC_AMPERSAND", "KC_AMPR"),
+    ("KC_ASTERISK", "KC_ASTR"),
+    ("KC_LEFT_PAREN", "KC_LPRN"),
+    ("KC_RIGHT_PAREN", "KC_RPRN"),
+    ("KC_LEFT_CURLY_BRACE", "KC_LCBR"),
+    ("KC_RIGHT_CURLY_BRACE", "KC_RCBR"),
+    ("KC_TILDE", "KC_TILD"),
+    ("KC_UNDERSCORE", "KC_UNDS"),
+    ("KC_COLON", "KC_COLN"),
+    ("KC_DOUBLE_QUOTE", "KC_DQUO"),
+    ("KC_DQT", "KC_DQUO"),
+    ("KC_LEFT_ANGLE_BRACKET", "KC_LT"),
+    ("KC_LABK", "KC_LT"),
+    ("KC_RIGHT_ANGLE_BRACKET", "KC_GT"),
+    ("KC_RABK", "KC_GT"),
+    ("KC_QUESTION", "KC_QUES"),
+    ("KC_AUDIO_VOL_UP", "KC_VOLU"),
+    ("KC_AUDIO_VOL_DOWN", "KC_VOLD"),
+    ("KC_AUDIO_MUTE", "KC_MUTE"),
+    ("KC_MEDIA_PLAY_PAUSE", "KC_MPLY"),
+    ("KC_MEDIA_NEXT_TRACK", "KC_MNXT"),
+    ("KC_MEDIA_PREV_TRACK", "KC_MPRV"),
+    ("KC_MEDIA_STOP", "KC_MSTP"),
+    ("KC_BRIGHTNESS_UP", "KC_BRIU"),
+    ("KC_BRIGHTNESS_DOWN", "KC_BRID"),
+    ("KC_TRANSPARENT", "KC_TRNS"),
+    ("_______", "KC_TRNS"),
+    ("XXXXXXX", "KC_NO"),
+    //short modifier names used in mod taps e.g. SFT_T
+    ("KC_SFT", "KC_LSFT"),
+    ("KC_CTL", "KC_LCTL"),
+    ("KC_ALT", "KC_LALT"),
+    ("KC_GUI", "KC_LGUI"),
+];
+
+///the short QMK name for a keycode, e.g. KC_ENTER to KC_ENT
+pub fn short_keycode(code: &str) -> &str {
+    QMK_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == code)
+        .map(|(_, short)| *short)
+        .unwrap_or(code)
+}
+
 fn nice_keycode(code: &str) -> PrintKey {
     match code {
         "KC_UP" => PrintKey::new("↑"),
@@ -224,3 +392,16 @@ fn nice_keycode(code: &str) -> PrintKey {
         _ => PrintKey::new(code),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_params() {
+        let ctx = Context::default();
+        let key = nice_code("LT(1, 4)", &ctx);
+        assert_eq!((key.middle.as_str(), key.bottom.as_str()), ("4", "L-1"));
+        assert!(matches!(KeyType::from("4"), KeyType::KeyCode(code) if code == "4"));
+    }
+}
@@ -1,29 +1,231 @@
-use myparser::{get_keymap, into_diagnostics, MyParser, Rule};
-use options::PrintOptions;
+use art::keymap_art;
+use cheatsheet::{html_page, markdown_tables};
+use context::Context;
+use drawer::drawer_yaml;
+use encoders::{add_encoders, encoder_string};
+use error::MyError;
+use graph::graph_dot;
+use kanata::{kanata_string, Dialect};
+use lint::lint;
+use myparser::{
+    checked_keymap_string, get_keymap, into_diagnostics, tidy_file, Keymap, MyParser, Rule,
+};
+use options::{PrintOptions, TableOptions};
 use pest::Parser;
+use profiles::{layout_name, profile_options};
+use tables::{get_tables, tables_string};
+use vial::get_vial_keymap;
 use wasm_bindgen::prelude::*;
+use zmk::{get_zmk_keymap, qmk_to_zmk, zmk_string};
 
+mod art;
+mod cheatsheet;
+mod context;
+mod drawer;
 mod drawsvg;
+mod encoders;
 mod error;
+mod graph;
+mod kanata;
 mod key;
+mod lint;
 mod myparser;
 mod options;
+mod preprocessor;
+mod profiles;
+mod tables;
+mod vial;
+mod zmk;
+fn parse_keymap(
+    example: &str,
+    encoder_map: &str,
+    source: &str,
+    ops: &PrintOptions,
+) -> Result<Keymap, MyError> {
+    let prog = MyParser::parse(Rule::programouter, example)?
+        .next()
+        .unwrap();
+    let mut keymap = get_keymap(prog, ops)?;
+    let encoders = MyParser::parse(Rule::encodermapouter, encoder_map)?
+        .next()
+        .filter(|p| p.as_rule() == Rule::encodermap);
+    if let Some(encoders) = encoders {
+        add_encoders(&mut keymap, encoders)?;
+    }
+    keymap.context = Context::from_source(source);
+    Ok(keymap)
+}
+
+///returns svg, tidy keymap, error, tidy encoder_map and warnings
+///source is the rest of keymap.c, e.g. tap dances, used to label keys
 #[wasm_bindgen]
-pub fn generate_svg(example: &str, ops: PrintOptions) -> Vec<String> {
-    let prog = match MyParser::parse(Rule::programouter, example) {
-        Ok(mut pairs) => pairs.next().unwrap(),
+pub fn generate_svg(
+    example: &str,
+    encoder_map: &str,
+    source: &str,
+    ops: &PrintOptions,
+) -> Vec<String> {
+    let keymap = match parse_keymap(example, encoder_map, source, ops) {
+        Ok(k) => k,
         Err(e) => {
-            println!("{}", into_diagnostics(&e));
-            return vec!["".to_string(), "".to_string(), e.to_string()];
+            println!("{e}");
+            return vec![
+                "".to_string(),
+                "".to_string(),
+                e.to_string(),
+                "".to_string(),
+            ];
         }
     };
-    let keymap = match get_keymap(prog, &ops) {
-        Ok(k) => k,
+    let svg = crate::drawsvg::create_svg(&keymap, ops);
+    let (out, error) = match checked_keymap_string(&keymap, ops) {
+        Ok(out) => (out, "".to_string()),
+        Err(e) => ("".to_string(), e.to_string()),
+    };
+    let encoders_out = encoder_string(&keymap, ops);
+    let warnings = lint(&keymap).join("\n");
+    vec![svg, out, error, encoders_out, warnings]
+}
+
+///graphviz dot of which keys reach which layers
+#[wasm_bindgen]
+pub fn layer_graph(example: &str, source: &str, ops: &PrintOptions) -> String {
+    match parse_keymap(example, "", source, ops) {
+        Ok(keymap) => graph_dot(&keymap),
+        Err(e) => e.to_string(),
+    }
+}
+
+///tidy the other arrays in keymap.c, e.g. combos, tap dances, key overrides and encoder_map
+#[wasm_bindgen]
+pub fn tidy_tables(example: &str, ops: &TableOptions) -> Vec<String> {
+    let pairs = match MyParser::parse(Rule::tablesouter, example) {
+        Ok(pairs) => pairs,
         Err(e) => {
-            return vec!["".to_string(), "".to_string(), e.to_string()];
+            return vec!["".to_string(), into_diagnostics(&e)];
         }
     };
-    let svg = crate::drawsvg::create_svg(&keymap, &ops);
-    let out = crate::myparser::keymap_string(&keymap, &ops);
-    vec![svg, out, "".to_string()]
+    match get_tables(pairs) {
+        Ok(tables) => vec![tables_string(&tables, ops), "".to_string()],
+        Err(e) => vec!["".to_string(), e.to_string()],
+    }
+}
+
+///returns svg, tidy .keymap and error for a ZMK .keymap file
+#[wasm_bindgen]
+pub fn generate_zmk_svg(example: &str, ops: &PrintOptions) -> Vec<String> {
+    let result = get_zmk_keymap(example, ops).and_then(|keymap| {
+        let out = zmk_string(example, ops)?;
+        Ok((crate::drawsvg::create_svg(&keymap, ops), out))
+    });
+    match result {
+        Ok((svg, out)) => vec![svg, out, "".to_string()],
+        Err(e) => vec!["".to_string(), "".to_string(), e.to_string()],
+    }
+}
+
+///returns a ZMK keymap node, error and warnings for keys with no ZMK equivalent
+#[wasm_bindgen]
+pub fn convert_to_zmk(example: &str, source: &str, ops: &PrintOptions) -> Vec<String> {
+    let result = parse_keymap(example, "", source, ops).and_then(|k| qmk_to_zmk(&k, ops));
+    match result {
+        Ok((out, warnings)) => vec![out, "".to_string(), warnings.join("\n")],
+        Err(e) => vec!["".to_string(), e.to_string(), "".to_string()],
+    }
+}
+
+///returns Kanata (or KMonad) defsrc, defalias and deflayer blocks, error and warnings
+#[wasm_bindgen]
+pub fn export_kanata(example: &str, source: &str, ops: &PrintOptions, kmonad: bool) -> Vec<String> {
+    let dialect = if kmonad {
+        Dialect::KMonad
+    } else {
+        Dialect::Kanata
+    };
+    let result =
+        parse_keymap(example, "", source, ops).and_then(|k| kanata_string(&k, ops, dialect));
+    match result {
+        Ok((out, warnings)) => vec![out, "".to_string(), warnings.join("\n")],
+        Err(e) => vec!["".to_string(), e.to_string(), "".to_string()],
+    }
+}
+
+///returns svg, tidy keymap, error, tidy encoder_map and warnings for a Vial .vil or VIA .json backup.
+///info is the keyboard's info.json, which maps the switch matrix to the LAYOUT arguments
+#[wasm_bindgen]
+pub fn import_vial(json: &str, info: &str, ops: &PrintOptions) -> Vec<String> {
+    match get_vial_keymap(json, info, ops) {
+        Ok((keymap, warnings)) => {
+            let (out, error) = match checked_keymap_string(&keymap, ops) {
+                Ok(out) => (out, "".to_string()),
+                Err(e) => ("".to_string(), e.to_string()),
+            };
+            vec![
+                crate::drawsvg::create_svg(&keymap, ops),
+                out,
+                error,
+                encoder_string(&keymap, ops),
+                warnings.join("\n"),
+            ]
+        }
+        Err(e) => vec![
+            "".to_string(),
+            "".to_string(),
+            e.to_string(),
+            "".to_string(),
+            "".to_string(),
+        ],
+    }
+}
+
+///returns keymap-drawer YAML and error
+#[wasm_bindgen]
+pub fn export_keymap_drawer(example: &str, source: &str, ops: &PrintOptions) -> Vec<String> {
+    match parse_keymap(example, "", source, ops) {
+        Ok(keymap) => vec![drawer_yaml(&keymap, ops), "".to_string()],
+        Err(e) => vec!["".to_string(), e.to_string()],
+    }
+}
+
+///returns markdown tables, a standalone html page and error
+#[wasm_bindgen]
+pub fn export_cheatsheet(example: &str, source: &str, ops: &PrintOptions) -> Vec<String> {
+    match parse_keymap(example, "", source, ops) {
+        Ok(keymap) => vec![
+            markdown_tables(&keymap),
+            html_page(&keymap, ops),
+            "".to_string(),
+        ],
+        Err(e) => vec!["".to_string(), "".to_string(), e.to_string()],
+    }
+}
+
+///returns each layer drawn as a box-drawn keyboard and error
+#[wasm_bindgen]
+pub fn draw_art(example: &str, source: &str, ops: &PrintOptions) -> Vec<String> {
+    match parse_keymap(example, "", source, ops) {
+        Ok(keymap) => vec![keymap_art(&keymap, ops), "".to_string()],
+        Err(e) => vec!["".to_string(), e.to_string()],
+    }
+}
+
+///ops with the built-in profile and .tidyqmk.toml settings for the keymap's LAYOUT or keyboard applied
+#[wasm_bindgen]
+pub fn apply_profile(
+    example: &str,
+    config: &str,
+    keyboard: &str,
+    ops: &PrintOptions,
+) -> Result<PrintOptions, String> {
+    let layout = layout_name(example).unwrap_or_default();
+    profile_options(ops, config, &layout, keyboard).map_err(|e| e.to_string())
+}
+
+///returns a whole keymap.c with its keymaps array tidied, and error
+#[wasm_bindgen]
+pub fn tidy_keymap_file(source: &str, ops: &PrintOptions) -> Vec<String> {
+    match tidy_file(source, ops) {
+        Ok(out) => vec![out, "".to_string()],
+        Err(e) => vec!["".to_string(), e.to_string()],
+    }
 }
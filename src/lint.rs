@@ -0,0 +1,428 @@
+use crate::{art::check_art, context::Context, myparser::Keymap};
+
+//basic keycodes, long and short names
+const BASIC: &[&str] = &[
+    "KC_NO",
+    "KC_TRNS",
+    "KC_TRANSPARENT",
+    "XXXXXXX",
+    "_______",
+    "KC_ENTER",
+    "KC_ENT",
+    "KC_ESCAPE",
+    "KC_ESC",
+    "KC_BACKSPACE",
+    "KC_BSPC",
+    "KC_TAB",
+    "KC_SPACE",
+    "KC_SPC",
+    "KC_MINUS",
+    "KC_MINS",
+    "KC_EQUAL",
+    "KC_EQL",
+    "KC_LEFT_BRACKET",
+    "KC_LBRC",
+    "KC_RIGHT_BRACKET",
+    "KC_RBRC",
+    "KC_BACKSLASH",
+    "KC_BSLS",
+    "KC_NONUS_HASH",
+    "KC_NUHS",
+    "KC_SEMICOLON",
+    "KC_SCLN",
+    "KC_QUOTE",
+    "KC_QUOT",
+    "KC_GRAVE",
+    "KC_GRV",
+    "KC_COMMA",
+    "KC_COMM",
+    "KC_DOT",
+    "KC_SLASH",
+    "KC_SLSH",
+    "KC_CAPS_LOCK",
+    "KC_CAPS",
+    "KC_PRINT_SCREEN",
+    "KC_PSCR",
+    "KC_SCROLL_LOCK",
+    "KC_SCRL",
+    "KC_PAUSE",
+    "KC_PAUS",
+    "KC_BRK",
+    "KC_INSERT",
+    "KC_INS",
+    "KC_HOME",
+    "KC_PAGE_UP",
+    "KC_PGUP",
+    "KC_DELETE",
+    "KC_DEL",
+    "KC_END",
+    "KC_PAGE_DOWN",
+    "KC_PGDN",
+    "KC_RIGHT",
+    "KC_RGHT",
+    "KC_LEFT",
+    "KC_DOWN",
+    "KC_UP",
+    "KC_NUM_LOCK",
+    "KC_NUM",
+    "KC_NONUS_BACKSLASH",
+    "KC_NUBS",
+    "KC_APPLICATION",
+    "KC_APP",
+    "KC_LEFT_CTRL",
+    "KC_LCTL",
+    "KC_LEFT_SHIFT",
+    "KC_LSFT",
+    "KC_LEFT_ALT",
+    "KC_LALT",
+    "KC_LOPT",
+    "KC_LEFT_GUI",
+    "KC_LGUI",
+    "KC_LCMD",
+    "KC_LWIN",
+    "KC_RIGHT_CTRL",
+    "KC_RCTL",
+    "KC_RIGHT_SHIFT",
+    "KC_RSFT",
+    "KC_RIGHT_ALT",
+    "KC_RALT",
+    "KC_ROPT",
+    "KC_ALGR",
+    "KC_RIGHT_GUI",
+    "KC_RGUI",
+    "KC_RCMD",
+    "KC_RWIN",
+    "KC_KB_POWER",
+    "KC_KB_MUTE",
+    "KC_KB_VOLUME_UP",
+    "KC_KB_VOLUME_DOWN",
+    "KC_AUDIO_MUTE",
+    "KC_MUTE",
+    "KC_AUDIO_VOL_UP",
+    "KC_VOLU",
+    "KC_AUDIO_VOL_DOWN",
+    "KC_VOLD",
+    "KC_MEDIA_NEXT_TRACK",
+    "KC_MNXT",
+    "KC_MEDIA_PREV_TRACK",
+    "KC_MPRV",
+    "KC_MEDIA_STOP",
+    "KC_MSTP",
+    "KC_MEDIA_PLAY_PAUSE",
+    "KC_MPLY",
+    "KC_MEDIA_SELECT",
+    "KC_MSEL",
+    "KC_MEDIA_EJECT",
+    "KC_EJCT",
+    "KC_MAIL",
+    "KC_CALCULATOR",
+    "KC_CALC",
+    "KC_MY_COMPUTER",
+    "KC_MYCM",
+    "KC_WWW_SEARCH",
+    "KC_WSCH",
+    "KC_WWW_HOME",
+    "KC_WHOM",
+    "KC_WWW_BACK",
+    "KC_WBAK",
+    "KC_WWW_FORWARD",
+    "KC_WFWD",
+    "KC_WWW_STOP",
+    "KC_WSTP",
+    "KC_WWW_REFRESH",
+    "KC_WREF",
+    "KC_WWW_FAVORITES",
+    "KC_WFAV",
+    "KC_MEDIA_FAST_FORWARD",
+    "KC_MFFD",
+    "KC_MEDIA_REWIND",
+    "KC_MRWD",
+    "KC_BRIGHTNESS_UP",
+    "KC_BRIU",
+    "KC_BRIGHTNESS_DOWN",
+    "KC_BRID",
+    "KC_CONTROL_PANEL",
+    "KC_CPNL",
+    "KC_ASSISTANT",
+    "KC_ASST",
+    "KC_MISSION_CONTROL",
+    "KC_MCTL",
+    "KC_LAUNCHPAD",
+    "KC_LPAD",
+    "KC_SYSTEM_POWER",
+    "KC_PWR",
+    "KC_SYSTEM_SLEEP",
+    "KC_SLEP",
+    "KC_SYSTEM_WAKE",
+    "KC_WAKE",
+    "KC_KP_SLASH",
+    "KC_PSLS",
+    "KC_KP_ASTERISK",
+    "KC_PAST",
+    "KC_KP_MINUS",
+    "KC_PMNS",
+    "KC_KP_PLUS",
+    "KC_PPLS",
+    "KC_KP_ENTER",
+    "KC_PENT",
+    "KC_KP_DOT",
+    "KC_PDOT",
+    "KC_KP_EQUAL",
+    "KC_PEQL",
+    "KC_KP_COMMA",
+    "KC_PCMM",
+    "KC_EXECUTE",
+    "KC_EXEC",
+    "KC_HELP",
+    "KC_MENU",
+    "KC_SELECT",
+    "KC_SLCT",
+    "KC_STOP",
+    "KC_AGAIN",
+    "KC_AGIN",
+    "KC_UNDO",
+    "KC_CUT",
+    "KC_COPY",
+    "KC_PASTE",
+    "KC_PSTE",
+    "KC_FIND",
+    "KC_POWER",
+    "KC_INTERNATIONAL_1",
+    "KC_INT1",
+    "KC_INTERNATIONAL_2",
+    "KC_INT2",
+    "KC_INTERNATIONAL_3",
+    "KC_INT3",
+    "KC_INTERNATIONAL_4",
+    "KC_INT4",
+    "KC_INTERNATIONAL_5",
+    "KC_INT5",
+    "KC_LANGUAGE_1",
+    "KC_LNG1",
+    "KC_LANGUAGE_2",
+    "KC_LNG2",
+    "KC_TILDE",
+    "KC_TILD",
+    "KC_EXCLAIM",
+    "KC_EXLM",
+    "KC_AT",
+    "KC_HASH",
+    "KC_DOLLAR",
+    "KC_DLR",
+    "KC_PERCENT",
+    "KC_PERC",
+    "KC_CIRCUMFLEX",
+    "KC_CIRC",
+    "KC_AMPERSAND",
+    "KC_AMPR",
+    "KC_ASTERISK",
+    "KC_ASTR",
+    "KC_LEFT_PAREN",
+    "KC_LPRN",
+    "KC_RIGHT_PAREN",
+    "KC_RPRN",
+    "KC_UNDERSCORE",
+    "KC_UNDS",
+    "KC_PLUS",
+    "KC_LEFT_CURLY_BRACE",
+    "KC_LCBR",
+    "KC_RIGHT_CURLY_BRACE",
+    "KC_RCBR",
+    "KC_PIPE",
+    "KC_COLON",
+    "KC_COLN",
+    "KC_DOUBLE_QUOTE",
+    "KC_DQUO",
+    "KC_DQT",
+    "KC_LEFT_ANGLE_BRACKET",
+    "KC_LABK",
+    "KC_LT",
+    "KC_RIGHT_ANGLE_BRACKET",
+    "KC_RABK",
+    "KC_GT",
+    "KC_QUESTION",
+    "KC_QUES",
+    "KC_MS_UP",
+    "KC_MS_DOWN",
+    "KC_MS_LEFT",
+    "KC_MS_RIGHT",
+    "KC_MS_BTN1",
+    "KC_MS_BTN2",
+    "KC_MS_BTN3",
+    "KC_MS_BTN4",
+    "KC_MS_BTN5",
+    "KC_MS_WH_UP",
+    "KC_MS_WH_DOWN",
+    "KC_MS_WH_LEFT",
+    "KC_MS_WH_RIGHT",
+    "KC_MS_ACCEL0",
+    "KC_MS_ACCEL1",
+    "KC_MS_ACCEL2",
+    "KC_MS_U",
+    "KC_MS_D",
+    "KC_MS_L",
+    "KC_MS_R",
+    "KC_BTN1",
+    "KC_BTN2",
+    "KC_BTN3",
+    "KC_BTN4",
+    "KC_BTN5",
+    "KC_WH_U",
+    "KC_WH_D",
+    "KC_WH_L",
+    "KC_WH_R",
+    "KC_ACL0",
+    "KC_ACL1",
+    "KC_ACL2",
+    "KC_LCAP",
+    "KC_LNUM",
+    "KC_LSCR",
+    "KC_LOCKING_CAPS_LOCK",
+    "KC_LOCKING_NUM_LOCK",
+    "KC_LOCKING_SCROLL_LOCK",
+    "KC_INTERNATIONAL_6",
+    "KC_INT6",
+    "KC_INTERNATIONAL_7",
+    "KC_INT7",
+    "KC_INTERNATIONAL_8",
+    "KC_INT8",
+    "KC_INTERNATIONAL_9",
+    "KC_INT9",
+    "KC_LANGUAGE_3",
+    "KC_LNG3",
+    "KC_LANGUAGE_4",
+    "KC_LNG4",
+    "KC_LANGUAGE_5",
+    "KC_LNG5",
+    "KC_LANGUAGE_6",
+    "KC_LNG6",
+    "KC_LANGUAGE_7",
+    "KC_LNG7",
+    "KC_LANGUAGE_8",
+    "KC_LNG8",
+    "KC_LANGUAGE_9",
+    "KC_LNG9",
+    "KC_ALTERNATE_ERASE",
+    "KC_ERAS",
+    "KC_SYSTEM_REQUEST",
+    "KC_SYRQ",
+    "KC_CANCEL",
+    "KC_CNCL",
+    "KC_CLEAR",
+    "KC_CLR",
+    "KC_PRIOR",
+    "KC_PRIR",
+    "KC_RETURN",
+    "KC_RETN",
+    "KC_SEPARATOR",
+    "KC_SEPR",
+    "KC_OUT",
+    "KC_OPER",
+    "KC_CLEAR_AGAIN",
+    "KC_CLAG",
+    "KC_CRSEL",
+    "KC_CRSL",
+    "KC_EXSEL",
+    "KC_EXSL",
+    "KC_KP_EQUAL_AS400",
+    //modifier combinations
+    "KC_MEH",
+    "KC_HYPR",
+    //space cadet and grave escape, also QK_ names
+    "KC_LSPO",
+    "KC_RSPC",
+    "KC_LCPO",
+    "KC_RCPC",
+    "KC_LAPO",
+    "KC_RAPC",
+    "KC_SFTENT",
+    "KC_GESC",
+    "KC_LEAD",
+];
+
+//whole families of keycodes we don't list one by one
+const PREFIXES: &[&str] = &[
+    "QK_", "RGB_", "RM_", "UG_", "MS_", "BL_", "AU_", "CK_", "MU_", "MI_", "DM_", "DT_", "SH_",
+    "OS_", "MAGIC_", "CM_", "HF_", "NK_", "EE_", "SQ_", "UC_", "AS_", "KO_", "AC_", "LM_", "CW_",
+    "TL_", "DB_", "JS_", "PB_", "PDF_", "LED_", "HPT_", "BT_", "OU_", "SE_",
+];
+
+//KC_F1..KC_F24, KC_P0..KC_P9 and KC_KP_0..KC_KP_9
+const NUMBERED: &[&str] = &["KC_F", "KC_P", "KC_KP_"];
+
+pub fn is_known_keycode(code: &str, ctx: &Context) -> bool {
+    let single = |c: &str| {
+        c.strip_prefix("KC_")
+            .is_some_and(|rest| rest.len() == 1 && rest.chars().all(|c| c.is_ascii_alphanumeric()))
+    };
+    BASIC.contains(&code)
+        || single(code)
+        || PREFIXES.iter().any(|p| code.starts_with(p))
+        || NUMBERED.iter().any(|p| numbered(code, p))
+        || ctx.custom_keycodes.iter().any(|c| c == code)
+        || ctx.defines.contains_key(code) && {
+            //only defines of keycodes, not e.g. #define MASK (1 << 2)
+            let expanded = ctx.expand(code);
+            expanded != code && (expanded.contains('(') || is_known_keycode(&expanded, ctx))
+        }
+}
+
+fn numbered(code: &str, prefix: &str) -> bool {
+    code.strip_prefix(prefix)
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+///keycodes that QMK won't know about, most likely typos
+pub fn lint(keymap: &Keymap) -> Vec<String> {
+    let mut warnings = vec![];
+    for layer in keymap.active_layers() {
+        for code in layer.active_keys().into_iter().flatten().flatten() {
+            let expanded = keymap.context.expand(code);
+            //only plain keycodes, function parameters can be layers, mods etc.
+            //ZMK behaviours we can't translate are left alone
+            if !expanded.contains('(')
+                && !expanded.starts_with('&')
+                && !is_known_keycode(&expanded, &keymap.context)
+            {
+                warnings.push(format!("Layer {}: unknown keycode {code}", layer.num));
+            }
+        }
+    }
+    warnings.extend(check_art(keymap));
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::myparser::{get_keymap, MyParser, Rule};
+    use crate::options::PrintOptions;
+    use pest::Parser;
+
+    #[test]
+    fn unknown_keycodes() {
+        let example = r#"
+            [0] = LAYOUT(KC_LSPO, KC_RSPC, KC_GESC, KC_MEH, KC_HYPR, KC_SFTENT,
+                MY_A, MASK, KC_AAA, MY_TYPO)
+        "#;
+        let source = r#"
+            #define MY_A KC_A
+            #define MASK (1 << 2)
+            #define MY_TYPO KC_BSPCE
+        "#;
+        let ops = PrintOptions::default();
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        let mut keymap = get_keymap(prog.next().unwrap(), &ops).unwrap();
+        keymap.context = Context::from_source(source);
+        assert_eq!(
+            lint(&keymap),
+            vec![
+                "Layer 0: unknown keycode MASK",
+                "Layer 0: unknown keycode KC_AAA",
+                "Layer 0: unknown keycode MY_TYPO",
+            ]
+        );
+        assert!(is_known_keycode("MY_A", &keymap.context));
+        assert!(!is_known_keycode("MY_TYPO", &keymap.context));
+    }
+}
@@ -1,19 +1,134 @@
+//the modules are shared with the wasm library, which uses the outputs the command line doesn't
+#![allow(dead_code)]
+
+use batch::keymap_files;
+use batch::summary;
+use batch::tidy_files;
+use batch::Outcome;
+use config::file_options;
+use config::Flags;
 use drawsvg::draw_keymap;
+use easier::prelude::*;
 use myparser::get_keymap;
 use myparser::into_diagnostics;
 use myparser::keymap_string;
+use myparser::tidy_file;
 use myparser::MyParser;
 use myparser::Rule;
 use options::PrintOptions;
 use pest::Parser;
 
+mod art;
+mod batch;
+mod config;
+mod context;
 mod drawsvg;
+mod encoders;
 mod error;
 mod key;
+mod lint;
 mod myparser;
 mod options;
+mod preprocessor;
+mod profiles;
+mod tables;
+mod zmk;
+
+const USAGE: &str = "Usage: tidyqmk [--check | --write] [--config FILE] [--keyboard NAME] [--SETTING VALUE]... KEYMAP.C|FOLDER...
+
+Prints the keymap with its keymaps array tidied. Settings come from the built-in
+profile for the keyboard, the nearest .tidyqmk.toml above the file, then the
+command line, e.g. --thumb_shift_in 2 --draw_nice true.
+Folders, e.g. qmk_firmware or a userspace, are searched for keymap.c files.
+--check lists the files that would change, the default for folders and several files.
+--write tidies them in place.
+With no arguments, prints and draws an example keymap.";
 
 fn main() {
+    let args = std::env::args().skip(1).to_vec();
+    if args.is_empty() {
+        demo();
+        return;
+    }
+    match run(&args) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    }
+}
+
+//false when checking found changes, or any file failed
+fn run(args: &[String]) -> Result<bool, String> {
+    let mut flags = Flags::default();
+    let mut paths = vec![];
+    let (mut check, mut write) = (false, false);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(name) = arg.strip_prefix("--") else {
+            paths.push(std::path::PathBuf::from(arg));
+            continue;
+        };
+        match name {
+            "help" => {
+                println!("{USAGE}");
+                return Ok(true);
+            }
+            "check" => {
+                check = true;
+                continue;
+            }
+            "write" => {
+                write = true;
+                continue;
+            }
+            _ => {}
+        }
+        let value = args
+            .next()
+            .ok_or(format!("--{name} needs a value\n\n{USAGE}"))?
+            .clone();
+        match name {
+            "config" => flags.config = Some(value.into()),
+            "keyboard" => flags.keyboard = Some(value),
+            _ => flags.settings.push((name.to_string(), value)),
+        }
+    }
+    if check && write {
+        return Err(format!("Use --check or --write, not both\n\n{USAGE}"));
+    }
+    if let [file] = paths.as_slice() {
+        if file.is_file() && !check && !write {
+            let source = std::fs::read_to_string(file)
+                .map_err(|e| format!("Can't read {}: {e}", file.display()))?;
+            let ops = file_options(file, &source, &flags).map_err(|e| e.to_string())?;
+            print!("{}", tidy_file(&source, &ops).map_err(|e| e.to_string())?);
+            return Ok(true);
+        }
+    }
+    if paths.is_empty() {
+        return Err(format!("Give a keymap file or folder\n\n{USAGE}"));
+    }
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            files.extend(keymap_files(&path).map_err(|e| e.to_string())?);
+        } else {
+            files.push(path);
+        }
+    }
+    let results = tidy_files(&files, &flags, write);
+    print!("{}", summary(&results, write));
+    Ok(results.iter().all(|(_, outcome)| match outcome {
+        Outcome::Changed => write,
+        Outcome::Unchanged => true,
+        Outcome::Failed(_) => false,
+    }))
+}
+
+fn demo() {
     let ops = PrintOptions::default();
     let example = r#"
 [0]=LAYOUT_split_3x6_3(
@@ -2,62 +2,333 @@ use easier::prelude::*;
 use pest::{
     error::{Error, ErrorVariant},
     iterators::Pair,
+    Parser,
 };
 use pest_derive::Parser;
 
-use crate::{error::MyError, key::nice_code, options::PrintOptions};
+use crate::{
+    art::{art_comment, column_widths, layer_art},
+    context::Context,
+    encoders::Encoder,
+    error::MyError,
+    key::nice_code,
+    options::PrintOptions,
+    preprocessor::{defined_symbols, Conditional, Item},
+};
 
 pub struct Keymap {
     pub layers: Vec<Layer>,
+    pub context: Context,
+    ///#if directives around whole layers
+    pub conditional: Conditional,
+    ///comments after the last layer
+    pub comments: Vec<String>,
 }
 pub struct Layer {
     pub num: String,
     name: String,
     pub keys: Vec<Vec<Option<String>>>,
+    pub encoders: Vec<Encoder>,
+    ///#if directives around rows
+    pub conditional: Conditional,
+    ///compiled with the defined symbols in PrintOptions
+    pub active: bool,
+    pub active_rows: Vec<bool>,
+    ///the box-drawing comment above the layer, kept or redrawn in the tidy keymap
+    pub art: Option<String>,
+    ///other comments above the layer
+    pub comments: Vec<String>,
+    ///comments on their own lines above each row and after the last, and at the end of each row
+    pub row_comments: RowComments,
+    ///drawn with a gap between the halves
+    pub split: bool,
+}
+
+pub struct RowComments {
+    pub above: Vec<Vec<String>>,
+    pub after: Vec<Vec<String>>,
+}
+
+impl RowComments {
+    pub fn new(rows: usize) -> RowComments {
+        RowComments {
+            above: vec![vec![]; rows + 1],
+            after: vec![vec![]; rows],
+        }
+    }
+}
+
+impl Keymap {
+    ///layers compiled with the defined symbols, used for drawing and checking
+    pub fn active_layers(&self) -> Vec<&Layer> {
+        self.layers.iter().filter(|l| l.active).to_vec()
+    }
+
+    ///keymap from layers and the #if directives around them
+    pub fn new(items: Vec<Item<Layer>>, ops: &PrintOptions) -> Result<Keymap, MyError> {
+        let (conditional, mut layers) = Conditional::new(items)?;
+        let active = conditional.active(&defined_symbols(&ops.defines));
+        for (layer, active) in layers.iter_mut().zip(active) {
+            layer.active = active;
+        }
+        align_unsplit(&mut layers);
+        let keymap = Keymap {
+            layers,
+            context: Context::default(),
+            conditional,
+            comments: vec![],
+        };
+
+        //check all layers have the same number of keys
+        let active = keymap.active_layers();
+        let Some(first) = active.first() else {
+            return Err("No layers are active with these defines".to_string().into());
+        };
+        let count = |layer: &Layer| {
+            layer
+                .active_keys()
+                .iter()
+                .map(|row| row.iter().flatten().count())
+                .sum::<usize>()
+        };
+        let num_keys = count(first);
+        for layer in active {
+            if count(layer) != num_keys {
+                return Err("All layers must have the same number of keys"
+                    .to_string()
+                    .into());
+            }
+        }
+
+        Ok(keymap)
+    }
+}
+
+impl Layer {
+    ///e.g. LAYOUT_split_3x6_3, or the node name for ZMK
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    ///a lowercase name for other formats, e.g. 0 to layer_0, _BASE to base
+    pub fn ident(&self) -> String {
+        if self.num.chars().all(|c| c.is_ascii_digit()) {
+            format!("layer_{}", self.num)
+        } else {
+            self.num.trim_matches('_').to_lowercase()
+        }
+    }
+
+    ///rows compiled with the defined symbols
+    pub fn active_keys(&self) -> Vec<&Vec<Option<String>>> {
+        self.keys
+            .iter()
+            .zip(&self.active_rows)
+            .filter(|(_, a)| **a)
+            .map(|(k, _)| k)
+            .to_vec()
+    }
+
+    ///layer from rows of keys and the #if directives around them, aligned into a grid,
+    ///with a gap between the halves if split
+    pub fn new(
+        num: String,
+        name: String,
+        rows: Vec<Item<Vec<String>>>,
+        split: bool,
+        ops: &PrintOptions,
+    ) -> Result<Layer, MyError> {
+        let (conditional, line_codes) = Conditional::new(rows)?;
+        validate(&line_codes)?;
+        let grid = if split {
+            let halves = ops.row_halves()?;
+            for (ri, (line, half)) in line_codes.iter().zip(&halves).enumerate() {
+                if let Some((left, right)) = half {
+                    if left + right != line.len() {
+                        return Err(format!(
+                            "Row {} of layer {num} has {} keys, not {left}/{right}",
+                            ri + 1,
+                            line.len()
+                        )
+                        .into());
+                    }
+                }
+            }
+            create_grid(line_codes, ops, &conditional, &halves, &ops.row_offsets()?)
+        } else {
+            unsplit_grid(line_codes)
+        };
+        let active_rows = conditional.active(&defined_symbols(&ops.defines));
+        let row_comments = RowComments::new(grid.len());
+        Ok(Layer {
+            keys: grid,
+            num,
+            name,
+            encoders: vec![],
+            conditional,
+            active: true,
+            active_rows,
+            art: None,
+            comments: vec![],
+            row_comments,
+            split,
+        })
+    }
 }
 
 pub fn get_keymap(pair: Pair<Rule>, ops: &PrintOptions) -> Result<Keymap, MyError> {
     assert!(pair.as_rule() == Rule::program);
-    let mut keymap = Keymap { layers: Vec::new() };
     //we go through layers
-    let inner = pair.into_inner();
-    for block in inner {
-        keymap.layers.push(get_layer(block, ops)?);
+    let mut items = vec![];
+    let input = pair.get_input();
+    let mut previous_end = 0;
+    for block in pair.into_inner() {
+        match block.as_rule() {
+            Rule::directive => items.push(Item::Directive(block.as_str().to_string())),
+            _ => {
+                //comments are skipped by the grammar, so look between the last layer and the first key
+                let first_key = block
+                    .clone()
+                    .into_inner()
+                    .flatten()
+                    .find(|p| matches!(p.as_rule(), Rule::key | Rule::directive))
+                    .map_or(block.as_span().end(), |p| p.as_span().start());
+                let before = &input[previous_end..first_key];
+                let (start, end) = (block.as_span().start(), block.as_span().end());
+                let mut layer = get_layer(block.clone(), ops)?;
+                let art_start = art_comment(before).and_then(|_| before.rfind("/*"));
+                layer.art = art_start.map(|i| {
+                    let art = art_comment(before).unwrap();
+                    dedent(input, previous_end + i, &art)
+                });
+                let (mut heading, inside): (Vec<_>, Vec<_>) = comments(&input[previous_end..end])
+                    .into_iter()
+                    .filter(|(i, _)| Some(*i) != art_start)
+                    .map(|(i, comment)| (previous_end + i, comment))
+                    .partition(|(i, _)| *i < start);
+                //a drawing from draw_nice right above the layer is drawn again
+                if ops.draw_nice {
+                    if let Some((i, comment)) = heading.last() {
+                        if input[i + comment.len()..start].trim().is_empty()
+                            && is_drawing(&dedent(input, *i, comment), &layer)
+                        {
+                            heading.pop();
+                        }
+                    }
+                }
+                layer.comments = heading
+                    .into_iter()
+                    .map(|(i, comment)| dedent(input, i, &comment))
+                    .to_vec();
+                layer.row_comments = row_comments(input, &block, inside)?;
+                previous_end = end;
+                items.push(Item::Item(layer));
+            }
+        }
     }
+    let mut keymap = Keymap::new(items, ops)?;
+    keymap.comments = comments(&input[previous_end..])
+        .into_iter()
+        .map(|(i, comment)| dedent(input, previous_end + i, &comment))
+        .to_vec();
+    Ok(keymap)
+}
 
-    //check all layers have the same number of keys
-    let num_keys = keymap.layers[0].keys.len();
-    for layer in &keymap.layers {
-        if layer.keys.len() != num_keys {
-            return Err("All layers must have the same number of keys"
-                .to_string()
-                .into());
+//which rows the comments inside a layer go above, or at the end of
+fn row_comments(
+    input: &str,
+    block: &Pair<Rule>,
+    comments: Vec<(usize, String)>,
+) -> Result<RowComments, MyError> {
+    //from the first key to the last
+    let rows = block
+        .clone()
+        .into_inner()
+        .flatten()
+        .filter(|p| p.as_rule() == Rule::line)
+        .map(|line| {
+            let keys = line
+                .into_inner()
+                .filter(|p| p.as_rule() == Rule::key)
+                .map(|p| (p.as_span().start(), p.as_span().end()))
+                .to_vec();
+            (keys[0].0, keys[keys.len() - 1].1)
+        })
+        .to_vec();
+    place_comments(input, &rows, comments)
+}
+
+///which rows, from where each starts to where it ends, the comments go above or at the end of
+pub fn place_comments(
+    input: &str,
+    rows: &[(usize, usize)],
+    comments: Vec<(usize, String)>,
+) -> Result<RowComments, MyError> {
+    let mut result = RowComments::new(rows.len());
+    for (at, comment) in comments {
+        let row = rows.iter().filter(|(start, _)| *start < at).count();
+        let comment = dedent(input, at, &comment);
+        match row.checked_sub(1).map(|r| rows[r]) {
+            Some((_, end)) if at < end => {
+                return Err(format!(
+                    "Can't keep the comment {comment} in the middle of a row of keys"
+                )
+                .into())
+            }
+            Some((_, end)) if !input[end..at].contains('\n') => result.after[row - 1].push(comment),
+            _ => result.above[row].push(comment),
         }
     }
+    Ok(result)
+}
 
-    Ok(keymap)
+//a comment the shape of what draw_nice writes above the layer, a line for each row and directive
+fn is_drawing(comment: &str, layer: &Layer) -> bool {
+    let directives =
+        layer.conditional.before.iter().flatten().count() + layer.conditional.after.len();
+    comment.starts_with("/*\n")
+        && comment.ends_with("\n*/")
+        && comment.lines().count() == layer.keys.len() + directives + 2
+}
+
+///the lines of a /* */ comment without the indent of the line it starts on, which the tidy file adds back
+pub fn dedent(input: &str, at: usize, comment: &str) -> String {
+    let line_start = input[..at].rfind('\n').map_or(0, |i| i + 1);
+    let indent = &input[line_start..at];
+    if !indent.trim().is_empty() {
+        return comment.to_string();
+    }
+    comment
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match line.strip_prefix(indent) {
+            Some(rest) if i > 0 => rest,
+            _ => line,
+        })
+        .to_vec()
+        .join("\n")
 }
+
 fn get_layer(pair: Pair<Rule>, ops: &PrintOptions) -> Result<Layer, MyError> {
     let mut inner = pair.into_inner();
     let num = inner.next().unwrap().as_str().to_string();
     let name = inner.next().unwrap().as_str().to_string();
     let lines = inner.next().unwrap().into_inner();
 
-    let mut line_codes = vec![];
+    let mut items = vec![];
     for line in lines {
+        if line.as_rule() == Rule::directive {
+            items.push(Item::Directive(line.as_str().to_string()));
+            continue;
+        }
         let mut keycodes = vec![];
         for keycode in line.into_inner() {
             keycodes.push(format_pair(keycode))
         }
-        line_codes.push(keycodes);
+        items.push(Item::Item(keycodes));
     }
-    validate(&line_codes)?;
-    let grid = create_grid(line_codes, ops);
-    Ok(Layer {
-        keys: grid,
-        num,
-        name,
-    })
+    let split = ops.board.is_split(&name);
+    Layer::new(num, name, items, split, ops)
 }
 
 pub fn format_pair(pair: pest::iterators::Pair<Rule>) -> String {
@@ -120,6 +391,72 @@ pub fn format_pair(pair: pest::iterators::Pair<Rule>) -> String {
         Rule::programouter => {}
         Rule::number => {}
         Rule::white => {}
+        Rule::term => {}
+        Rule::operator => {}
+        //tables are formatted in tables.rs
+        Rule::tablesouter => {}
+        Rule::table => {}
+        Rule::statement => {}
+        Rule::braces => {}
+        Rule::skipped => {}
+        Rule::declaration => {}
+        Rule::declword => {}
+        Rule::cast => {}
+        Rule::tablebody => {}
+        Rule::entry => {}
+        Rule::value => {}
+        Rule::reference => {}
+        Rule::sourceouter => {}
+        Rule::cenum => {}
+        Rule::enumentry => {}
+        Rule::label => {}
+        Rule::labeltext => {}
+        Rule::spaces => {}
+        Rule::sendcase => {}
+        Rule::define => {}
+        Rule::directive => {}
+        Rule::defineargs => {}
+        Rule::definebody => {}
+        Rule::sendstring => {}
+        Rule::sendname => {}
+        Rule::string => {}
+        Rule::stringtext => {}
+        //encoders are formatted in encoders.rs
+        Rule::encodermapouter => {}
+        Rule::encodermap => {}
+        Rule::encoderblock => {}
+        Rule::encoder => {}
+        //ZMK keymaps are read in zmk.rs
+        Rule::zmkouter => {}
+        Rule::zmkkeymap => {}
+        Rule::zmklayer => {}
+        Rule::zmknode => {}
+        Rule::zmkproperty => {}
+        Rule::zmkbindings => {}
+        Rule::zmkrow => {}
+        Rule::zmkbinding => {}
+        Rule::zmkbehavior => {}
+        Rule::zmkparam => {}
+        //Vial and VIA backups are read in vial.rs
+        Rule::jsonouter => {}
+        Rule::jvalue => {}
+        Rule::jobject => {}
+        Rule::jpair => {}
+        Rule::jarray => {}
+        Rule::jnumber => {}
+        Rule::jliteral => {}
+        Rule::tomlouter => {}
+        Rule::tomlskip => {}
+        Rule::tomltable => {}
+        Rule::tomlkey => {}
+        Rule::tomlbare => {}
+        Rule::tomlpair => {}
+        Rule::tomlbool => {}
+        Rule::keymapsouter => {}
+        Rule::keymaps => {}
+        Rule::keymapsopen => {}
+        Rule::commentsouter => {}
+        Rule::comment => {}
     }
     result
 }
@@ -140,44 +477,160 @@ fn validate(line_codes: &[Vec<String>]) -> Result<(), MyError> {
 //if we shift in thumb cols, we want to add space to each other row on the inside
 //we align each column to the centre and add padding to outside if needed
 //if uneven number of keys, we may have to pad keys
-fn create_grid(line_codes: Vec<Vec<String>>, ops: &PrintOptions) -> Vec<Vec<Option<String>>> {
-    let rows = line_codes.len();
-    let mut grid = line_codes
+fn create_grid(
+    line_codes: Vec<Vec<String>>,
+    ops: &PrintOptions,
+    conditional: &Conditional,
+    halves: &[Option<(usize, usize)>],
+    offsets: &[Option<(isize, isize)>],
+) -> Vec<Vec<Option<String>>> {
+    //how far each side of a row moves in, thumb rows are the last rows, whichever #if branch they are in
+    let offsets = (0..line_codes.len())
+        .map(|li| {
+            offsets.get(li).copied().flatten().unwrap_or_else(|| {
+                let shift = ops.thumb_shift_in as isize;
+                if conditional.later_count(li) >= ops.number_of_thumbs {
+                    (0, 0)
+                } else {
+                    (shift, shift)
+                }
+            })
+        })
+        .to_vec();
+    let max_left = offsets.iter().map(|o| o.0).max().unwrap_or(0);
+    let max_right = offsets.iter().map(|o| o.1).max().unwrap_or(0);
+
+    //the halves of a row are padded on the outside to the same width,
+    //when not given the extra key of an odd row goes on the left.
+    //the row that moves in furthest touches the centre, the others get space on the inside
+    let sides = line_codes
         .into_iter()
-        .map(|a| a.into_iter().map(Some).to_vec())
+        .zip(&offsets)
+        .enumerate()
+        .map(|(li, (line, (left_offset, right_offset)))| {
+            let (left, right) = halves
+                .get(li)
+                .copied()
+                .flatten()
+                .unwrap_or((line.len() - line.len() / 2, line.len() / 2));
+            let half = left.max(right);
+            let mut codes = line.into_iter().map(Some);
+            let mut left_side = vec![None; half - left];
+            left_side.extend(codes.by_ref().take(left));
+            left_side.extend(std::iter::repeat_n(None, (max_left - left_offset) as usize));
+            let mut right_side = vec![None; (max_right - right_offset) as usize];
+            right_side.extend(codes);
+            right_side.extend(std::iter::repeat_n(None, half - right));
+            (left_side, right_side)
+        })
         .to_vec();
 
-    //make sure even number in grid (sometimes less columns on one side)
-    for line in grid.iter_mut() {
-        if line.len() % 2 != 0 {
-            line.push(None);
-        }
-    }
+    //both sides as wide as the widest, so the centre is the split
+    let width = sides
+        .iter()
+        .map(|(l, r)| l.len().max(r.len()))
+        .max()
+        .unwrap();
+    sides
+        .into_iter()
+        .map(|(left, right)| {
+            let mut line = vec![None; width - left.len()];
+            line.extend(left);
+            line.extend(right);
+            line.extend(std::iter::repeat_n(None, 2 * width - line.len()));
+            line
+        })
+        .to_vec()
+}
 
-    //if thumb shift, we add to each row inside except thumb row
-    for (li, line) in grid.iter_mut().enumerate() {
-        if li < rows - ops.number_of_thumbs {
-            for _ in 0..ops.thumb_shift_in * 2 {
-                //add to centre
-                let centre = line.len() / 2;
-                line.insert(centre, None);
+//a board in one piece has no halves to centre, so keys go where they sit on the board.
+//we don't know key sizes, so a short row gives the spare width to its space bar, or its edge keys,
+//e.g. caps lock and enter on an ANSI home row. widths are in quarter keys
+fn unsplit_grid(line_codes: Vec<Vec<String>>) -> Vec<Vec<Option<String>>> {
+    let columns = line_codes.iter().map(|l| l.len()).max().unwrap();
+    line_codes
+        .into_iter()
+        .map(|line| {
+            let extra = (columns - line.len()) * 4;
+            let mut widths = vec![4; line.len()];
+            let space = line
+                .iter()
+                .position(|c| c.contains("KC_SPC") || c.contains("KC_SPACE"));
+            match space {
+                Some(i) => widths[i] += extra,
+                None => {
+                    widths[0] += extra / 2;
+                    *widths.last_mut().unwrap() += extra - extra / 2;
+                }
             }
-        }
-    }
+            let mut row = vec![None; columns];
+            let mut x = 0;
+            let last = line.len() - 1;
+            for (i, (code, width)) in line.into_iter().zip(widths).enumerate() {
+                //a wide key on the right edge, e.g. right shift, still ends the row,
+                //the space bar goes in the middle of its width
+                let column = if i == last {
+                    columns - 1
+                } else if Some(i) == space {
+                    (x + width / 2) / 4
+                } else {
+                    (x + 2) / 4
+                };
+                row[column] = Some(code);
+                x += width;
+            }
+            row
+        })
+        .to_vec()
+}
 
-    let max_cols = grid.iter().map(|x| x.len()).max().unwrap();
-    for line in grid.iter_mut() {
-        while line.len() < max_cols {
-            line.push(None);
-            line.insert(0, None);
+//the layers of a board in one piece share its key positions, but often only one layer has the space bar.
+//each row is placed like the first layer that has a space in it
+fn align_unsplit(layers: &mut [Layer]) {
+    if layers.first().is_none_or(|l| l.split) {
+        return;
+    }
+    let is_space = |c: &Option<String>| {
+        c.as_ref()
+            .is_some_and(|c| c.contains("KC_SPC") || c.contains("KC_SPACE"))
+    };
+    for ri in 0..layers[0].keys.len() {
+        let reference = layers
+            .iter()
+            .find(|l| l.keys.get(ri).is_some_and(|row| row.iter().any(is_space)))
+            .unwrap_or(&layers[0]);
+        let Some(reference) = reference.keys.get(ri) else {
+            continue;
+        };
+        let positions = reference
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.is_some())
+            .map(|(i, _)| i)
+            .to_vec();
+        let width = reference.len();
+        for layer in layers.iter_mut() {
+            let Some(row) = layer.keys.get_mut(ri) else {
+                continue;
+            };
+            let codes = row.iter().flatten().cloned().to_vec();
+            if codes.len() == positions.len() {
+                *row = vec![None; width];
+                for (code, &i) in codes.into_iter().zip(&positions) {
+                    row[i] = Some(code);
+                }
+            }
         }
     }
-
-    grid
 }
 
 pub fn keymap_string(keymap: &Keymap, ops: &PrintOptions) -> String {
-    let column_count = keymap.layers[0].keys[0].len();
+    let column_count = keymap
+        .layers
+        .iter()
+        .flat_map(|l| l.keys.iter().map(|row| row.len()))
+        .max()
+        .unwrap_or_default();
 
     let layer_keys = keymap
         .layers
@@ -186,39 +639,228 @@ pub fn keymap_string(keymap: &Keymap, ops: &PrintOptions) -> String {
         .to_vec();
 
     let column_layer_lengths = get_column_layer_lengths(&layer_keys, column_count);
+    let art_widths = column_widths(&layer_keys, &keymap.context, ops);
     let mut result = String::new();
 
     for (layi, layer) in layer_keys.iter().enumerate() {
+        for directive in &keymap.conditional.before[layi] {
+            result.push_str(&format!("{directive}\n"));
+        }
+        for comment in &keymap.layers[layi].comments {
+            result.push_str(&format!("{comment}\n"));
+        }
         let mut layer_string = String::new();
         layer_string.push_str(&format!(
             "[{}] = {} (\n",
             keymap.layers[layi].num, keymap.layers[layi].name
         ));
-        layer_string.push_str(&layout_keys(layer, ops, &column_layer_lengths, layi, ","));
+        layer_string.push_str(&layout_keys(
+            layer,
+            &keymap.layers[layi],
+            ops,
+            &column_layer_lengths,
+            layi,
+            ",",
+            true,
+        ));
 
-        if ops.draw_nice {
+        let art = &keymap.layers[layi].art;
+        if ops.box_art && (ops.draw_nice || art.is_some()) {
+            result.push_str("/*\n");
+            let split = keymap.layers[layi].split;
+            result.push_str(&layer_art(layer, split, &art_widths, &keymap.context, ops));
+            result.push_str("*/\n");
+        } else if ops.draw_nice {
             let layer_keys_nice = layer
                 .iter()
                 .map(|r| {
                     r.iter()
-                        .map(|k| k.as_ref().map(|a| nice_code(a).middle.clone()))
+                        .map(|k| {
+                            k.as_ref()
+                                .map(|a| nice_code(a, &keymap.context).middle.clone())
+                        })
                         .to_vec()
                 })
                 .to_vec();
-            let nice = layout_keys(&layer_keys_nice, ops, &column_layer_lengths, layi, " ");
+            let nice = layout_keys(
+                &layer_keys_nice,
+                &keymap.layers[layi],
+                ops,
+                &column_layer_lengths,
+                layi,
+                " ",
+                false,
+            );
 
             result.push_str("/*\n");
             result.push_str(&nice);
             result.push_str("*/\n");
+        } else if let Some(art) = art {
+            result.push_str(art);
+            result.push('\n');
         }
         result.push_str(&layer_string);
         result.push(')');
         result.push_str(",\n");
     }
+    for directive in &keymap.conditional.after {
+        result.push_str(&format!("{directive}\n"));
+    }
+    for comment in &keymap.comments {
+        result.push_str(&format!("{comment}\n"));
+    }
+
+    result
+}
+
+///keymap_string, refusing to output anything that does not parse back to the same layers and keys
+pub fn checked_keymap_string(keymap: &Keymap, ops: &PrintOptions) -> Result<String, MyError> {
+    let out = keymap_string(keymap, ops);
+    let bug = |what: String| -> MyError {
+        format!("Not output, tidying would change {what}. This is a bug, please report it with your keymap")
+            .into()
+    };
+    let reparsed = MyParser::parse(Rule::programouter, &out)
+        .map_err(|e| {
+            bug(format!(
+                "the keymap so it no longer parses ({})",
+                into_diagnostics(&e)
+            ))
+        })?
+        .next()
+        .unwrap();
+    let reparsed = get_keymap(reparsed, ops).map_err(|e| bug(format!("the keymap ({e})")))?;
+    let (before, after) = (semantics(keymap), semantics(&reparsed));
+    if before.len() != after.len() {
+        return Err(bug(format!("{} layers to {}", before.len(), after.len())));
+    }
+    for ((num, name, keys), (new_num, new_name, new_keys)) in before.iter().zip(&after) {
+        if num != new_num || name != new_name {
+            return Err(bug(format!("[{num}] = {name} to [{new_num}] = {new_name}")));
+        }
+        if keys != new_keys {
+            let i = keys
+                .iter()
+                .zip(new_keys)
+                .take_while(|(a, b)| a == b)
+                .count();
+            let key = |keys: &[String]| keys.get(i).cloned().unwrap_or("nothing".to_string());
+            return Err(bug(format!(
+                "key {} of layer {num} from {} to {}",
+                i + 1,
+                key(keys),
+                key(new_keys)
+            )));
+        }
+    }
+    let (before, after) = (kept_comments(keymap), kept_comments(&reparsed));
+    if let Some(lost) = before.iter().find(|c| !after.contains(c)) {
+        return Err(bug(format!("the keymap so it loses the comment {lost}")));
+    }
+    if before != after {
+        return Err(bug("the order of the comments".to_string()));
+    }
+    Ok(out)
+}
+
+///each comment in the text and where it starts
+pub fn comments(text: &str) -> Vec<(usize, String)> {
+    let Ok(pairs) = MyParser::parse(Rule::commentsouter, text) else {
+        return vec![];
+    };
+    pairs
+        .flatten()
+        .filter(|p| p.as_rule() == Rule::comment)
+        .map(|p| (p.as_span().start(), p.as_str().to_string()))
+        .to_vec()
+}
 
+///a whole keymap.c with the layers in its keymaps array tidied, and everything else left alone
+pub fn tidy_file(source: &str, ops: &PrintOptions) -> Result<String, MyError> {
+    let pairs = MyParser::parse(Rule::keymapsouter, source)?;
+    let Some(keymaps) = pairs.into_iter().find(|p| p.as_rule() == Rule::keymaps) else {
+        return Err("No keymaps array found".to_string().into());
+    };
+    //between the braces, the same text as pasted into the page
+    let start = keymaps.clone().into_inner().next().unwrap().as_span().end();
+    let end = keymaps.as_span().end() - 1;
+    let prog = MyParser::parse(Rule::programouter, &source[start..end])?
+        .next()
+        .unwrap();
+    let mut keymap = get_keymap(prog, ops)?;
+    keymap.context = Context::from_source(source);
+    let mut result = source[..start].to_string();
+    result.push('\n');
+    for line in checked_keymap_string(&keymap, ops)?.lines() {
+        match line.trim_end() {
+            "" => result.push('\n'),
+            //#ifdef and the like stay in column 0, as in QMK sources
+            line if line.starts_with('#') => result.push_str(&format!("{line}\n")),
+            line => result.push_str(&format!("    {line}\n")),
+        }
+    }
+    result.push_str(&source[end..]);
+    Ok(result)
+}
+
+//every comment kept in the tidy keymap, in order, which tidying must not change either
+fn kept_comments(keymap: &Keymap) -> Vec<String> {
+    let mut result = vec![];
+    for layer in &keymap.layers {
+        result.extend(layer.comments.iter().cloned());
+        let rows = &layer.row_comments;
+        for (li, above) in rows.above.iter().enumerate() {
+            result.extend(above.iter().cloned());
+            result.extend(rows.after.get(li).into_iter().flatten().cloned());
+        }
+    }
+    result.extend(keymap.comments.iter().cloned());
     result
 }
 
+//layer numbers, names and keys in order, what tidying must not change
+fn semantics(keymap: &Keymap) -> Vec<(String, String, Vec<String>)> {
+    keymap
+        .layers
+        .iter()
+        .map(|l| {
+            let keys = l.keys.iter().flatten().flatten().cloned().to_vec();
+            (l.num.clone(), l.name.clone(), keys)
+        })
+        .to_vec()
+}
+
+///each layer's keys laid out in aligned columns, without the layer header
+pub fn layers_string(keymap: &Keymap, ops: &PrintOptions, sep: &str) -> Vec<String> {
+    let column_count = keymap
+        .layers
+        .iter()
+        .flat_map(|l| l.keys.iter().map(|row| row.len()))
+        .max()
+        .unwrap_or_default();
+    let layer_keys = keymap
+        .layers
+        .iter()
+        .map(|l| l.keys.into_vec_cloned())
+        .to_vec();
+    let column_layer_lengths = get_column_layer_lengths(&layer_keys, column_count);
+    layer_keys
+        .iter()
+        .enumerate()
+        .map(|(layi, layer)| {
+            layout_keys(
+                layer,
+                &keymap.layers[layi],
+                ops,
+                &column_layer_lengths,
+                layi,
+                sep,
+                false,
+            )
+        })
+        .to_vec()
+}
+
 fn get_column_layer_lengths(
     keymap: &[Vec<Vec<Option<String>>>],
     column_count: usize,
@@ -227,11 +869,16 @@ fn get_column_layer_lengths(
         .iter()
         .map(|layer| {
             layer.iter().fold(
-                std::iter::repeat(0).take(column_count).collect(),
+                std::iter::repeat_n(0, column_count).collect(),
                 |acc: Vec<usize>, line| {
                     acc.iter()
-                        .zip(line.iter())
-                        .map(|(a, l)| l.as_ref().map(|a| a.len()).unwrap_or_default().max(*a))
+                        .enumerate()
+                        .map(|(i, a)| {
+                            line.get(i)
+                                .and_then(|l| l.as_ref())
+                                .map_or(0, |l| l.len())
+                                .max(*a)
+                        })
                         .collect()
                 },
             )
@@ -239,15 +886,32 @@ fn get_column_layer_lengths(
         .to_vec()
 }
 
+//grid is the layer's keys, or labels for them
 fn layout_keys(
     grid: &[Vec<Option<String>>],
+    layer: &Layer,
     ops: &PrintOptions,
     column_layer_lens: &[Vec<usize>],
     layi: usize,
     sep: &str,
+    with_comments: bool,
 ) -> String {
+    let (split, conditional) = (layer.split, &layer.conditional);
+    let no_comments = RowComments::new(grid.len());
+    let comments = if with_comments {
+        &layer.row_comments
+    } else {
+        &no_comments
+    };
     let mut layer_string = String::new();
     for (li, line) in grid.iter().enumerate() {
+        for directive in &conditional.before[li] {
+            layer_string.push_str(&format!("{directive}\n"));
+        }
+        for comment in &comments.above[li] {
+            layer_string.push_str(&format!("{comment}\n"));
+        }
+        let mut row = String::new();
         for (i, code) in line.iter().enumerate() {
             let max_len = if ops.align_layers {
                 column_layer_lens.iter().map(|x| x[i]).max().unwrap()
@@ -257,28 +921,38 @@ fn layout_keys(
             let width = max_len + 1;
             let centre = line.len() / 2;
             let mut comma = sep;
-            //check if there are no more buttons after this
-            if li == grid.len() - 1 && line.iter().skip(i + 1).all(|x| x.is_none()) {
+            //check if there are no more buttons after this, in this #if branch
+            if conditional.later_count(li) == 0 && line.iter().skip(i + 1).all(|x| x.is_none()) {
                 comma = "";
             }
             match code {
                 Some(code) => {
-                    if i >= centre || ops.left_align {
-                        layer_string.push_str(&format!("{: <1$}{comma}", code, width));
+                    if i >= centre || ops.left_align || !split {
+                        row.push_str(&format!("{: <1$}{comma}", code, width));
                     } else {
-                        layer_string.push_str(&format!("{: >1$}{comma}", code, width));
+                        row.push_str(&format!("{: >1$}{comma}", code, width));
                     }
                 }
-                None => layer_string.push_str(&format!("{: ^1$}", "  ", width + 1)), //+1 for the comma that is missing here
+                None => row.push_str(&format!("{: ^1$}", "  ", width + 1)), //+1 for the comma that is missing here
             }
 
-            if i == centre - 1 {
+            if split && i == centre - 1 {
                 let space = " ".repeat(ops.split_space);
-                layer_string.push_str(&space);
+                row.push_str(&space);
             }
         }
+        match comments.after[li].join(" ") {
+            after if after.is_empty() => layer_string.push_str(&row),
+            after => layer_string.push_str(&format!("{} {after}", row.trim_end())),
+        }
         layer_string.push('\n');
     }
+    for directive in &conditional.after {
+        layer_string.push_str(&format!("{directive}\n"));
+    }
+    for comment in &comments.above[grid.len()] {
+        layer_string.push_str(&format!("{comment}\n"));
+    }
     layer_string
 }
 
@@ -325,11 +999,13 @@ pub fn into_diagnostics(e: &Error<Rule>) -> String {
 pub struct MyParser;
 
 #[cfg(test)]
+#[allow(clippy::iter_nth_zero)]
 mod tests {
 
     use pest::Parser;
 
     use super::*;
+    use crate::options::Board;
 
     #[test]
     fn test_grammar_keycode() {
@@ -386,6 +1062,15 @@ mod tests {
         assert_eq!(pairs.nth(0).unwrap().into_inner().count(), 5);
     }
 
+    #[test]
+    fn trailing_line_comments() {
+        //the newline after a comment still ends the row
+        let example = "[0] = LAYOUT(KC_A, KC_B, // top\nKC_C, KC_D // bottom\n)";
+        let mut pairs = MyParser::parse(Rule::programouter, example).unwrap();
+        let keymap = get_keymap(pairs.next().unwrap(), &PrintOptions::default()).unwrap();
+        assert_eq!(keymap.layers[0].keys.len(), 2);
+    }
+
     #[test]
     fn two_lines() {
         let example = r#"KC_Q, KC_W,
@@ -543,4 +1228,462 @@ mod tests {
 "#
         );
     }
+
+    #[test]
+    fn conditional_compilation() {
+        let example = r#"
+            [0] = LAYOUT_universal(
+            KC_A, KC_B,
+            #ifdef RGB_MATRIX_ENABLE
+            RGB_TOG, RGB_MOD
+            #else
+            KC_C, KC_D
+            #endif
+            ),
+            #if defined(EXTRA)
+            [1] = LAYOUT_universal( KC_1, KC_2,
+            KC_3, KC_4 ),
+            #endif
+        "#;
+        let mut ops = PrintOptions {
+            thumb_shift_in: 0,
+            ..Default::default()
+        };
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        let keymap = get_keymap(prog.next().unwrap(), &ops).unwrap();
+        assert_eq!(keymap.active_layers().len(), 1);
+        let rows = keymap.layers[0].active_keys();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][0].as_deref(), Some("KC_C"));
+        assert_eq!(
+            keymap_string(&keymap, &ops),
+            r#"[0] = LAYOUT_universal (
+    KC_A,     KC_B    ,
+#ifdef RGB_MATRIX_ENABLE
+ RGB_TOG,     RGB_MOD 
+#else
+    KC_C,     KC_D    
+#endif
+),
+#if defined(EXTRA)
+[1] = LAYOUT_universal (
+    KC_1,     KC_2    ,
+    KC_3,     KC_4    
+),
+#endif
+"#
+        );
+
+        ops.defines = "RGB_MATRIX_ENABLE EXTRA".to_string();
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        let keymap = get_keymap(prog.next().unwrap(), &ops).unwrap();
+        assert_eq!(keymap.active_layers().len(), 2);
+        assert_eq!(
+            keymap.layers[0].active_keys()[1][0].as_deref(),
+            Some("RGB_TOG")
+        );
+    }
+
+    fn parse(example: &str, ops: &PrintOptions) -> Keymap {
+        let mut prog = MyParser::parse(Rule::programouter, example)
+            .unwrap_or_else(|e| panic!("{e}\n{example}"));
+        get_keymap(prog.next().unwrap(), ops).unwrap_or_else(|e| panic!("{e}\n{example}"))
+    }
+
+    //tidy output is a fixed point and keeps every key
+    fn check_idempotent(example: &str, ops: &PrintOptions) {
+        let keymap = parse(example, ops);
+        let once = keymap_string(&keymap, ops);
+        let again = parse(&once, ops);
+        assert_eq!(
+            semantics(&keymap),
+            semantics(&again),
+            "keys changed:\n{once}"
+        );
+        let twice = keymap_string(&again, ops);
+        assert_eq!(once, twice, "not idempotent for:\n{example}");
+    }
+
+    //xorshift, so the generated keymaps are the same every run
+    struct Random(u64);
+    impl Random {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    #[test]
+    fn idempotent_generated() {
+        const KEYS: &[&str] = &[
+            "KC_A",
+            "KC_TRNS",
+            "_______",
+            "XXXXXXX",
+            "KC_AUDIO_VOL_UP",
+            "LT(1,KC_SPC)",
+            "LSFT_T(KC_A)",
+            "MO(2)",
+            "S(KC_1)",
+            "LCTL(LSFT(KC_T))",
+            "QK_BOOT",
+            "KC_LBRC",
+            "MT(MOD_LCTL | MOD_LSFT,KC_ESC)",
+        ];
+        let mut random = Random(0x2545F4914F6CDD1D);
+        for _ in 0..200 {
+            let rows = (0..1 + random.below(5))
+                .map(|_| 1 + random.below(14))
+                .to_vec();
+            //a row with an #ifdef around it, the same one in every layer
+            let conditional_row = random.below(rows.len() + 1);
+            let mut example = String::new();
+            for layi in 0..1 + random.below(4) {
+                let conditional_layer = layi > 0 && random.below(4) == 0;
+                if conditional_layer {
+                    example.push_str("#ifdef EXTRA_LAYER\n");
+                }
+                example.push_str(&format!("[{layi}] = LAYOUT_{}(", rows.len()));
+                for (ri, row) in rows.iter().enumerate() {
+                    example.push('\n');
+                    if ri == conditional_row {
+                        example.push_str("#ifdef EXTRA_ROW\n");
+                    }
+                    let keys = (0..*row).map(|_| KEYS[random.below(KEYS.len())]).to_vec();
+                    example.push_str(&keys.join(", "));
+                    if ri + 1 < rows.len() || random.below(2) == 0 {
+                        example.push(',');
+                    }
+                    if ri == conditional_row {
+                        example.push_str("\n#endif");
+                    }
+                }
+                //a directive runs to the end of its line
+                example.push_str("\n),\n");
+                if conditional_layer {
+                    example.push_str("#endif\n");
+                }
+            }
+            let ops = PrintOptions {
+                thumb_shift_in: random.below(3),
+                left_align: random.below(2) == 0,
+                split_space: random.below(8),
+                align_layers: random.below(2) == 0,
+                number_of_thumbs: random.below(3),
+                draw_nice: random.below(2) == 0,
+                box_art: random.below(2) == 0,
+                defines: ["", "EXTRA_ROW", "EXTRA_LAYER"][random.below(3)].to_string(),
+                board: [Board::Auto, Board::Split, Board::Unsplit][random.below(3)],
+                row_offsets: ["", "1/-1", "-1, 2/0", "0/3, , 1"][random.below(4)].to_string(),
+                ..Default::default()
+            };
+            check_idempotent(&example, &ops);
+        }
+    }
+
+    #[test]
+    fn idempotent_corpus() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/corpus");
+        let mut count = 0;
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "c") {
+                let example = std::fs::read_to_string(&path).unwrap();
+                for ops in [
+                    PrintOptions::default(),
+                    PrintOptions {
+                        thumb_shift_in: 0,
+                        number_of_thumbs: 0,
+                        ..Default::default()
+                    },
+                ] {
+                    check_idempotent(&example, &ops);
+                }
+                count += 1;
+            }
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn refuses_changed_keys() {
+        let ops = PrintOptions::default();
+        let mut keymap = parse("[0] = LAYOUT(KC_A, KC_B, KC_C, KC_D)", &ops);
+        assert!(checked_keymap_string(&keymap, &ops).is_ok());
+        //a key that would print as two
+        keymap.layers[0].keys[0][1] = Some("KC_B, KC_E".to_string());
+        assert_eq!(
+            checked_keymap_string(&keymap, &ops)
+                .unwrap_err()
+                .to_string(),
+            "Not output, tidying would change key 2 of layer 0 from KC_B, KC_E to KC_B. \
+             This is a bug, please report it with your keymap"
+        );
+    }
+
+    //UPDATE_SNAPSHOTS=1 cargo test accepts the current output
+    #[test]
+    fn snapshots() {
+        let corpus = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus");
+        let snapshots = corpus.join("snapshots");
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        let mut paths = std::fs::read_dir(&corpus)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().is_some_and(|e| e == "c"))
+            .to_vec();
+        paths.sort();
+        let mut changed = vec![];
+        for path in paths {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            let text = std::fs::read_to_string(&path).unwrap();
+            //each keymap is named after its keyboard, so it gets the built-in profile
+            let layout = crate::profiles::layout_name(&text).unwrap_or_default();
+            let ops =
+                crate::profiles::profile_options(&PrintOptions::default(), "", &layout, &name)
+                    .unwrap();
+            let keymap = parse(&text, &ops);
+            //updating can't tell a misaligned board, but rows above the thumbs reach both edges
+            let thumb_rows = match name.as_str() {
+                "ergodox" => 3,
+                "corne" | "kyria" | "lily58" | "sofle" => 1,
+                _ => 0,
+            };
+            for layer in &keymap.layers {
+                for (ri, row) in layer.keys[..layer.keys.len() - thumb_rows]
+                    .iter()
+                    .enumerate()
+                {
+                    assert!(
+                        row.first().is_some_and(Option::is_some)
+                            && row.last().is_some_and(Option::is_some),
+                        "{name}: row {} of layer {} doesn't reach the edges, check its profile",
+                        ri + 1,
+                        layer.num
+                    );
+                }
+            }
+            let outputs = [
+                (
+                    format!("{name}.tidy.c"),
+                    checked_keymap_string(&keymap, &ops).unwrap(),
+                ),
+                (
+                    format!("{name}.svg"),
+                    crate::drawsvg::create_svg(&keymap, &ops),
+                ),
+            ];
+            for (file, actual) in outputs {
+                let snapshot = snapshots.join(&file);
+                let new = snapshots.join(format!("{file}.new"));
+                if update {
+                    std::fs::write(&snapshot, &actual).unwrap();
+                    let _ = std::fs::remove_file(&new);
+                } else if std::fs::read_to_string(&snapshot).ok().as_ref() != Some(&actual) {
+                    //written next to the snapshot so it can be diffed
+                    std::fs::write(&new, &actual).unwrap();
+                    changed.push(file);
+                }
+            }
+        }
+        assert!(
+            changed.is_empty(),
+            "snapshots changed: {changed:?}, compare the .new files in corpus/snapshots \
+             and run UPDATE_SNAPSHOTS=1 cargo test to accept them"
+        );
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let source = r#"#include QMK_KEYBOARD_H
+
+// the layers
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+    // letters
+    /*
+     * ,-----------.
+     * |  A  |  B  |
+     * |-----+-----|
+     * |  C  |  D  |
+     * `-----------'
+     */
+    [0] = LAYOUT(
+        KC_A,KC_B, // top
+        // bottom row
+        KC_C,KC_D
+    ),
+    /* numbers,
+       for maths */
+    [1] = LAYOUT(KC_1,KC_2,
+        KC_3,KC_4)
+    // the end
+};
+"#;
+        for ops in [
+            PrintOptions::default(),
+            PrintOptions {
+                draw_nice: true,
+                ..Default::default()
+            },
+        ] {
+            let tidy = tidy_file(source, &ops).unwrap();
+            for comment in [
+                "\n    // letters\n",
+                "KC_B , // top\n",
+                "\n    // bottom row\n",
+                "\n    /* numbers,\n       for maths */\n",
+                "\n    // the end\n",
+            ] {
+                assert!(tidy.contains(comment), "{comment} lost from:\n{tidy}");
+            }
+            assert_eq!(tidy_file(&tidy, &ops).unwrap(), tidy);
+        }
+        let tidy = tidy_file(source, &PrintOptions::default()).unwrap();
+        assert!(tidy.contains("\n    /*\n     * ,-----------.\n     * |  A  |  B  |\n"));
+        let inside = source.replace("KC_C,KC_D", "KC_C, /* left */ KC_D");
+        assert_eq!(
+            tidy_file(&inside, &PrintOptions::default())
+                .unwrap_err()
+                .to_string(),
+            "Can't keep the comment /* left */ in the middle of a row of keys"
+        );
+    }
+
+    #[test]
+    fn tidy_file_directives() {
+        let source = r#"const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+    [0] = LAYOUT(
+        KC_A, KC_B,
+    #ifdef EXTRA_ROW
+        KC_C, KC_D,
+    #endif
+        KC_E, KC_F
+    ),
+#ifdef NAV_ENABLE
+    [1] = LAYOUT(KC_1, KC_2,
+        KC_3, KC_4)
+#endif
+};
+"#;
+        let ops = PrintOptions {
+            defines: "EXTRA_ROW".to_string(),
+            ..Default::default()
+        };
+        let tidy = tidy_file(source, &ops).unwrap();
+        let directives = tidy.lines().filter(|l| l.contains('#')).to_vec();
+        assert_eq!(
+            directives,
+            ["#ifdef EXTRA_ROW", "#endif", "#ifdef NAV_ENABLE", "#endif"]
+        );
+        assert_eq!(tidy_file(&tidy, &ops).unwrap(), tidy);
+    }
+
+    #[test]
+    fn unsplit_rows() {
+        let example = r#"[0] = LAYOUT(
+            KC_Q, KC_W, KC_E, KC_R, KC_T, KC_Y, KC_U,
+            KC_A, KC_S, KC_D, KC_F, KC_G,
+            KC_LCTL, KC_SPC, KC_RCTL)"#;
+        let ops = PrintOptions {
+            board: Board::Unsplit,
+            ..Default::default()
+        };
+        let keymap = parse(example, &ops);
+        let rows = keymap.layers[0]
+            .keys
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|k| k.as_deref().unwrap_or("-"))
+                    .to_vec()
+                    .join(" ")
+            })
+            .to_vec();
+        //short rows put their spare width on the edge keys, or either side of the space bar
+        assert_eq!(
+            rows[1..],
+            [
+                "KC_A - KC_S KC_D KC_F - KC_G",
+                "KC_LCTL - - KC_SPC - - KC_RCTL"
+            ]
+        );
+    }
+
+    #[test]
+    fn split_layout_names() {
+        for (layout, split) in [
+            ("LAYOUT_split_3x6_3", true),
+            ("LAYOUT_split_3x5_2", true),
+            ("LAYOUT", true),
+            ("LAYOUT_60_ansi", false),
+            ("LAYOUT_60_ansi_split_bs_rshift", false),
+            ("LAYOUT_65_ansi_blocker_split_bs", false),
+            ("LAYOUT_tkl_ansi_split_bs", false),
+            ("LAYOUT_ortho_4x12", false),
+        ] {
+            assert_eq!(Board::Auto.is_split(layout), split, "{layout}");
+        }
+    }
+
+    #[test]
+    fn asymmetric_halves() {
+        let example = r#"[0] = LAYOUT(
+            KC_Q, KC_W, KC_E, KC_R, KC_T, KC_Y, KC_U,
+            KC_A, KC_S, KC_D, KC_F, KC_G, KC_H,
+            KC_SPC, KC_ENT)"#;
+        let ops = PrintOptions {
+            halves: "3/4, 3/3".to_string(),
+            thumb_shift_in: 0,
+            ..Default::default()
+        };
+        let keymap = parse(example, &ops);
+        assert_eq!(
+            keymap_string(&keymap, &ops),
+            "[0] = LAYOUT (
+   KC_Q, KC_W,   KC_E,     KC_R   ,KC_T ,KC_Y ,KC_U ,
+   KC_A, KC_S,   KC_D,     KC_F   ,KC_G ,KC_H ,      
+               KC_SPC,     KC_ENT                   
+),
+"
+        );
+        let ops = PrintOptions {
+            halves: "4/4".to_string(),
+            ..Default::default()
+        };
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        assert_eq!(
+            get_keymap(prog.next().unwrap(), &ops)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Row 1 of layer 0 has 7 keys, not 4/4"
+        );
+    }
+
+    #[test]
+    fn row_offsets() {
+        let example = r#"[0] = LAYOUT(
+            KC_Q, KC_W, KC_E, KC_R, KC_T, KC_Y,
+            KC_A, KC_S, KC_D, KC_F, KC_G, KC_H,
+            KC_1, KC_2, KC_3, KC_4,
+            KC_5, KC_6)"#;
+        //the last two rows are thumbs, one further in on the right, the first row out by one on the left
+        let ops = PrintOptions {
+            row_offsets: "-1/0, 0, 1/2, 2".to_string(),
+            ..Default::default()
+        };
+        let keymap = parse(example, &ops);
+        assert_eq!(
+            keymap_string(&keymap, &ops),
+            "[0] = LAYOUT (
+ KC_Q, KC_W, KC_E,                                   KC_R ,KC_T ,KC_Y ,  
+       KC_A, KC_S, KC_D,                             KC_F ,KC_G ,KC_H ,  
+                   KC_1, KC_2,           KC_3 ,KC_4 ,                    
+                               KC_5,     KC_6                           
+),
+"
+        );
+    }
 }
@@ -1,6 +1,9 @@
 use wasm_bindgen::prelude::*;
 
+use crate::error::MyError;
+
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct PrintOptions {
     pub thumb_shift_in: usize,
     pub left_align: bool,
@@ -8,6 +11,23 @@ pub struct PrintOptions {
     pub align_layers: bool,
     pub number_of_thumbs: usize,
     pub draw_nice: bool,
+    ///draw the nice labels as a box-drawn keyboard
+    pub box_art: bool,
+    ///add hold legends under the tap labels in the box-drawn keyboard
+    pub hold_legends: bool,
+    ///split keyboards get a gap between the halves, Auto guesses from the LAYOUT name
+    pub board: Board,
+    ///keys on the left and right half of each row of a split board, e.g. "6/7, 6/7, 6/6, 3/3".
+    ///rows left out are cut in the middle
+    #[wasm_bindgen(getter_with_clone)]
+    pub halves: String,
+    ///how far each row of a split board moves in towards the centre on the left and right, e.g. "0/0, 0/0, 1/1, 2/-1".
+    ///negative moves out, rows left out use number_of_thumbs and thumb_shift_in
+    #[wasm_bindgen(getter_with_clone)]
+    pub row_offsets: String,
+    ///symbols defined for #ifdef branches in the keymap, e.g. "RGB_MATRIX_ENABLE"
+    #[wasm_bindgen(getter_with_clone)]
+    pub defines: String,
 }
 #[wasm_bindgen]
 impl PrintOptions {
@@ -17,6 +37,45 @@ impl PrintOptions {
     }
 }
 
+impl PrintOptions {
+    ///left and right key counts for each row from halves, None for rows cut in the middle
+    pub fn row_halves(&self) -> Result<Vec<Option<(usize, usize)>>, MyError> {
+        row_pairs(&self.halves)
+            .ok_or_else(|| format!("Halves should look like 6/7, 6/6, not {}", self.halves).into())
+    }
+
+    ///left and right inward shifts for each row from row_offsets, None for rows using thumb_shift_in
+    pub fn row_offsets(&self) -> Result<Vec<Option<(isize, isize)>>, MyError> {
+        row_pairs(&self.row_offsets).ok_or_else(|| {
+            format!(
+                "Row offsets should look like 0/0, 1/-1, 2, not {}",
+                self.row_offsets
+            )
+            .into()
+        })
+    }
+}
+
+//e.g. "6/7, , 3" to [Some((6, 7)), None, Some((3, 3))]
+fn row_pairs<T: std::str::FromStr + Copy>(text: &str) -> Option<Vec<Option<(T, T)>>> {
+    if text.trim().is_empty() {
+        return Some(vec![]);
+    }
+    text.split(',')
+        .map(|row| {
+            let row = row.trim();
+            if row.is_empty() {
+                return Some(None);
+            }
+            let (left, right) = row.split_once('/').unwrap_or((row, row));
+            Some(Some((
+                left.trim().parse().ok()?,
+                right.trim().parse().ok()?,
+            )))
+        })
+        .collect()
+}
+
 impl Default for PrintOptions {
     fn default() -> Self {
         PrintOptions {
@@ -26,6 +85,67 @@ impl Default for PrintOptions {
             align_layers: true,
             number_of_thumbs: 1,
             draw_nice: false,
+            box_art: false,
+            hold_legends: false,
+            board: Board::Auto,
+            halves: String::new(),
+            row_offsets: String::new(),
+            defines: String::new(),
+        }
+    }
+}
+
+///whether the keyboard has two halves
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Board {
+    Auto,
+    Split,
+    Unsplit,
+}
+
+//parts of LAYOUT names of boards in one piece, e.g. LAYOUT_60_ansi, LAYOUT_ortho_4x12, LAYOUT_planck_grid
+const UNSPLIT_LAYOUTS: &[&str] = &[
+    "60", "65", "75", "tkl", "ortho", "planck", "grid", "mit", "ansi", "iso", "numpad", "all",
+];
+
+impl Board {
+    ///whether a layer with this LAYOUT name is drawn with a gap between the halves
+    pub fn is_split(self, layout: &str) -> bool {
+        match self {
+            Board::Split => true,
+            Board::Unsplit => false,
+            Board::Auto => {
+                //split right after LAYOUT, as LAYOUT_60_ansi_split_bs_rshift has a split backspace
+                let layout = layout.to_lowercase();
+                let mut parts = layout.split('_').skip_while(|part| *part == "layout");
+                parts.clone().next() == Some("split")
+                    || !parts.any(|part| UNSPLIT_LAYOUTS.iter().any(|u| part.starts_with(u)))
+            }
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct TableOptions {
+    pub indent: usize,
+    pub align_columns: bool,
+    pub align_tables: bool,
+}
+#[wasm_bindgen]
+impl TableOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TableOptions {
+        Default::default()
+    }
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        TableOptions {
+            indent: 4,
+            align_columns: true,
+            align_tables: true,
         }
     }
 }
@@ -0,0 +1,252 @@
+use easier::prelude::*;
+
+use crate::error::MyError;
+
+/// Which #if branches a sequence of items (layers or rows) sits in,
+/// and the directive lines to print around them
+#[derive(Default)]
+pub struct Conditional {
+    ///directive lines before each item
+    pub before: Vec<Vec<String>>,
+    ///directive lines after the last item
+    pub after: Vec<String>,
+    ///for each item, the (group, branch) of every #if it is inside
+    paths: Vec<Vec<(usize, usize)>>,
+    ///the condition of each branch of each #if group
+    groups: Vec<Vec<String>>,
+}
+
+pub enum Item<T> {
+    Directive(String),
+    Item(T),
+}
+
+impl Conditional {
+    ///split items from directives, keeping track of which branches the items are in
+    pub fn new<T>(
+        items: impl IntoIterator<Item = Item<T>>,
+    ) -> Result<(Conditional, Vec<T>), MyError> {
+        let mut cond = Conditional::default();
+        let mut stack: Vec<(usize, usize)> = vec![];
+        let mut pending = vec![];
+        let mut values = vec![];
+        for item in items {
+            match item {
+                Item::Item(value) => {
+                    cond.before.push(std::mem::take(&mut pending));
+                    cond.paths.push(stack.clone());
+                    values.push(value);
+                }
+                Item::Directive(line) => {
+                    let (name, rest) = split_directive(&line);
+                    match name.as_str() {
+                        "if" | "ifdef" | "ifndef" => {
+                            let condition = match name.as_str() {
+                                "ifdef" => format!("defined({rest})"),
+                                "ifndef" => format!("!defined({rest})"),
+                                _ => rest,
+                            };
+                            stack.push((cond.groups.len(), 0));
+                            cond.groups.push(vec![condition]);
+                        }
+                        "elif" | "else" => {
+                            let Some((group, branch)) = stack.last_mut() else {
+                                return Err(format!("#{name} without #if").into());
+                            };
+                            *branch += 1;
+                            let condition = if name == "else" {
+                                "1".to_string()
+                            } else {
+                                rest
+                            };
+                            cond.groups[*group].push(condition);
+                        }
+                        "endif" => {
+                            stack
+                                .pop()
+                                .ok_or_else(|| "#endif without #if".to_string())?;
+                        }
+                        _ => {}
+                    }
+                    pending.push(line.trim().to_string());
+                }
+            }
+        }
+        if !stack.is_empty() {
+            return Err("#if without #endif".to_string().into());
+        }
+        cond.after = pending;
+        Ok((cond, values))
+    }
+
+    ///true if both items can be compiled at the same time
+    pub fn together(&self, a: usize, b: usize) -> bool {
+        self.paths[a].iter().all(|(group, branch)| {
+            self.paths[b]
+                .iter()
+                .all(|(g, br)| g != group || br == branch)
+        })
+    }
+
+    ///how many items after this one can be compiled with it
+    pub fn later_count(&self, i: usize) -> usize {
+        (i + 1..self.paths.len())
+            .filter(|j| self.together(i, *j))
+            .count()
+    }
+
+    ///whether each item is compiled, given the defined symbols
+    pub fn active(&self, defined: &[String]) -> Vec<bool> {
+        //the branch taken for each group
+        let taken = self
+            .groups
+            .iter()
+            .map(|branches| branches.iter().position(|c| evaluate(c, defined)))
+            .to_vec();
+        self.paths
+            .iter()
+            .map(|path| path.iter().all(|(g, b)| taken[*g] == Some(*b)))
+            .to_vec()
+    }
+}
+
+//e.g. "#  ifdef RGB_MATRIX_ENABLE" to ("ifdef", "RGB_MATRIX_ENABLE")
+fn split_directive(line: &str) -> (String, String) {
+    let line = line.trim().trim_start_matches('#').trim_start();
+    let line = line.split("//").next().unwrap().split("/*").next().unwrap();
+    let name = line
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect::<String>();
+    let rest = line[name.len()..].trim().to_string();
+    (name, rest)
+}
+
+///symbols the user has defined, e.g. "RGB_MATRIX_ENABLE, ENCODER_ENABLE"
+pub fn defined_symbols(defines: &str) -> Vec<String> {
+    defines
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .to_vec()
+}
+
+///evaluate simple #if conditions: defined(X), X, numbers, !, &&, || and brackets
+pub fn evaluate(condition: &str, defined: &[String]) -> bool {
+    let tokens = tokenize(condition);
+    let mut pos = 0;
+    parse_or(&tokens, &mut pos, defined)
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let chars = text.chars().to_vec();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else if (c == '&' || c == '|') && chars.get(i + 1) == Some(&c) {
+            tokens.push(format!("{c}{c}"));
+            i += 2;
+        } else {
+            tokens.push(c.to_string());
+            i += 1;
+        }
+    }
+    tokens
+}
+
+fn parse_or(tokens: &[String], pos: &mut usize, defined: &[String]) -> bool {
+    let mut value = parse_and(tokens, pos, defined);
+    while tokens.get(*pos).is_some_and(|t| t == "||") {
+        *pos += 1;
+        let right = parse_and(tokens, pos, defined);
+        value = value || right;
+    }
+    value
+}
+
+fn parse_and(tokens: &[String], pos: &mut usize, defined: &[String]) -> bool {
+    let mut value = parse_unary(tokens, pos, defined);
+    while tokens.get(*pos).is_some_and(|t| t == "&&") {
+        *pos += 1;
+        let right = parse_unary(tokens, pos, defined);
+        value = value && right;
+    }
+    value
+}
+
+fn parse_unary(tokens: &[String], pos: &mut usize, defined: &[String]) -> bool {
+    let Some(token) = tokens.get(*pos) else {
+        return false;
+    };
+    *pos += 1;
+    match token.as_str() {
+        "!" => !parse_unary(tokens, pos, defined),
+        "(" => {
+            let value = parse_or(tokens, pos, defined);
+            if tokens.get(*pos).is_some_and(|t| t == ")") {
+                *pos += 1;
+            }
+            value
+        }
+        "defined" => {
+            let bracket = tokens.get(*pos).is_some_and(|t| t == "(");
+            if bracket {
+                *pos += 1;
+            }
+            let value = tokens.get(*pos).is_some_and(|t| defined.contains(t));
+            *pos += 1;
+            if bracket && tokens.get(*pos).is_some_and(|t| t == ")") {
+                *pos += 1;
+            }
+            value
+        }
+        t if t.chars().all(|c| c.is_ascii_digit()) => t != "0",
+        t => defined.iter().any(|d| d == t),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditions() {
+        let defined = defined_symbols("RGB_MATRIX_ENABLE, ENCODER_ENABLE");
+        assert!(evaluate("defined(RGB_MATRIX_ENABLE)", &defined));
+        assert!(evaluate(
+            "defined RGB_MATRIX_ENABLE && !defined(OLED_ENABLE)",
+            &defined
+        ));
+        assert!(evaluate("OLED_ENABLE || (ENCODER_ENABLE && 1)", &defined));
+        assert!(!evaluate("0", &defined));
+    }
+
+    #[test]
+    fn branches() {
+        let items = vec![
+            Item::Item("a"),
+            Item::Directive("#ifdef X".to_string()),
+            Item::Item("b"),
+            Item::Directive("#else".to_string()),
+            Item::Item("c"),
+            Item::Directive("#endif".to_string()),
+        ];
+        let (cond, values) = Conditional::new(items).unwrap();
+        assert_eq!(values, vec!["a", "b", "c"]);
+        assert!(!cond.together(1, 2));
+        assert_eq!(cond.later_count(0), 2);
+        assert_eq!(cond.later_count(1), 0);
+        assert_eq!(cond.active(&[]), vec![true, false, true]);
+        assert_eq!(cond.active(&["X".to_string()]), vec![true, true, false]);
+        assert_eq!(cond.after, vec!["#endif"]);
+    }
+}
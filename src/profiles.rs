@@ -0,0 +1,252 @@
+use easier::prelude::*;
+use pest::{iterators::Pair, Parser};
+
+use crate::{
+    error::MyError,
+    myparser::{MyParser, Rule},
+    options::{Board, PrintOptions},
+};
+
+//settings for popular boards, by keyboard folder in qmk_firmware or LAYOUT name.
+//the same format as .tidyqmk.toml, which is applied after these
+const BUILT_IN: &str = r#"
+[profile.LAYOUT_split_3x6_3] # corne
+board = "split"
+number_of_thumbs = 1
+thumb_shift_in = 1
+
+[profile.LAYOUT_split_3x5_3] # corne with 5 columns
+board = "split"
+number_of_thumbs = 1
+thumb_shift_in = 1
+
+[profile.LAYOUT_split_3x5_2] # ferris, sweep
+board = "split"
+number_of_thumbs = 1
+thumb_shift_in = 0
+
+[profile.lily58]
+board = "split"
+row_offsets = "0, 0, 0, 1, 1"
+
+[profile.sofle]
+board = "split"
+row_offsets = "0, 0, 0, 1, 1"
+
+[profile.kyria]
+board = "split"
+row_offsets = "0, 0, 2, 2"
+
+[profile.LAYOUT_ergodox_pretty] # the bottom row and home row are shorter on the inside
+board = "split"
+row_offsets = "0, 0, -1, 0, -2, 0, 0, 0"
+
+[profile.planck]
+board = "unsplit"
+"#;
+
+pub(crate) enum Value {
+    Text(String),
+    Number(String),
+    Bool(bool),
+}
+
+//e.g. [profile.kyria] and its settings, settings before any table have no name
+struct Table {
+    name: Vec<String>,
+    settings: Vec<(String, Value)>,
+}
+
+///options for a keymap, from the built-in profiles and a .tidyqmk.toml.
+///[default] applies to every keymap, [profile.NAME] when NAME is the LAYOUT name or part of the keyboard, e.g. crkbd
+pub fn profile_options(
+    ops: &PrintOptions,
+    config: &str,
+    layout: &str,
+    keyboard: &str,
+) -> Result<PrintOptions, MyError> {
+    let matches = |name: &[String]| match name {
+        [profile, n] if profile == "profile" => {
+            //crkbd/rev1, or kyria in splitkb/kyria/rev3
+            n == layout
+                || keyboard == n
+                || keyboard.starts_with(&format!("{n}/"))
+                || keyboard.split('/').any(|part| part == n)
+        }
+        _ => false,
+    };
+    let mut result = ops.clone();
+    for table in parse_config(BUILT_IN)? {
+        if matches(&table.name) {
+            apply(&mut result, &table.settings)?;
+        }
+    }
+    let config = parse_config(config)?;
+    for table in &config {
+        if table.name.is_empty() || table.name == ["default"] {
+            apply(&mut result, &table.settings)?;
+        } else if !matches!(table.name.as_slice(), [profile, _] if profile == "profile") {
+            return Err(format!(
+                "Unknown table [{}], use [default] or [profile.NAME]",
+                table.name.join(".")
+            )
+            .into());
+        }
+    }
+    for table in &config {
+        if matches(&table.name) {
+            apply(&mut result, &table.settings)?;
+        }
+    }
+    Ok(result)
+}
+
+///the LAYOUT name of the first layer, used to pick a profile
+pub fn layout_name(example: &str) -> Option<String> {
+    //a whole keymap.c, or just the layers
+    let keymaps = MyParser::parse(Rule::keymapsouter, example)
+        .ok()?
+        .find(|p| p.as_rule() == Rule::keymaps);
+    let program = match keymaps {
+        Some(keymaps) => keymaps
+            .into_inner()
+            .find(|p| p.as_rule() == Rule::program)?,
+        None => MyParser::parse(Rule::programouter, example).ok()?.next()?,
+    };
+    let block = program
+        .into_inner()
+        .find(|p| p.as_rule() == Rule::layerblock)?;
+    Some(block.into_inner().nth(1)?.as_str().to_string())
+}
+
+fn parse_config(text: &str) -> Result<Vec<Table>, MyError> {
+    let mut tables = vec![Table {
+        name: vec![],
+        settings: vec![],
+    }];
+    for pair in MyParser::parse(Rule::tomlouter, text)? {
+        match pair.as_rule() {
+            Rule::tomltable => tables.push(Table {
+                name: pair.into_inner().map(toml_key).to_vec(),
+                settings: vec![],
+            }),
+            Rule::tomlpair => {
+                let mut inner = pair.into_inner();
+                let key = toml_key(inner.next().unwrap());
+                let value = inner.next().unwrap();
+                let value = match value.as_rule() {
+                    Rule::string => Value::Text(value.into_inner().as_str().to_string()),
+                    Rule::tomlbool => Value::Bool(value.as_str() == "true"),
+                    _ => Value::Number(value.as_str().to_string()),
+                };
+                tables.last_mut().unwrap().settings.push((key, value));
+            }
+            _ => {}
+        }
+    }
+    Ok(tables)
+}
+
+//bare or quoted
+fn toml_key(pair: Pair<Rule>) -> String {
+    let key = pair.into_inner().next().unwrap();
+    match key.as_rule() {
+        Rule::string => key.into_inner().as_str().to_string(),
+        _ => key.as_str().to_string(),
+    }
+}
+
+pub(crate) fn apply(ops: &mut PrintOptions, settings: &[(String, Value)]) -> Result<(), MyError> {
+    for (key, value) in settings {
+        let number = || match value {
+            Value::Number(n) => n
+                .parse::<usize>()
+                .map_err(|_| format!("{key} should be a whole number, not {n}")),
+            _ => Err(format!("{key} should be a number")),
+        };
+        let flag = || match value {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(format!("{key} should be true or false")),
+        };
+        let text = || match value {
+            Value::Text(t) => Ok(t.clone()),
+            _ => Err(format!("{key} should be in quotes")),
+        };
+        match key.as_str() {
+            "thumb_shift_in" => ops.thumb_shift_in = number()?,
+            "split_space" => ops.split_space = number()?,
+            "number_of_thumbs" => ops.number_of_thumbs = number()?,
+            "left_align" => ops.left_align = flag()?,
+            "align_layers" => ops.align_layers = flag()?,
+            "draw_nice" => ops.draw_nice = flag()?,
+            "box_art" => ops.box_art = flag()?,
+            "hold_legends" => ops.hold_legends = flag()?,
+            "halves" => ops.halves = text()?,
+            "row_offsets" => ops.row_offsets = text()?,
+            "defines" => ops.defines = text()?,
+            "board" => {
+                ops.board = match text()?.to_lowercase().as_str() {
+                    "auto" => Board::Auto,
+                    "split" => Board::Split,
+                    "unsplit" => Board::Unsplit,
+                    other => {
+                        return Err(
+                            format!("board should be auto, split or unsplit, not {other}").into(),
+                        )
+                    }
+                }
+            }
+            _ => return Err(format!("Unknown setting {key}").into()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles() {
+        let config = r#"
+# shared by all our keymaps
+[default]
+split_space = 3
+draw_nice = true
+
+[profile."crkbd/rev1"]
+thumb_shift_in = 2
+"#;
+        let ops = PrintOptions::default();
+        let corne = profile_options(&ops, config, "LAYOUT_split_3x6_3", "crkbd/rev1").unwrap();
+        assert_eq!(
+            (
+                corne.split_space,
+                corne.draw_nice,
+                corne.thumb_shift_in,
+                corne.board
+            ),
+            (3, true, 2, Board::Split)
+        );
+        let kyria = profile_options(&ops, config, "LAYOUT", "splitkb/kyria/rev3").unwrap();
+        assert_eq!(
+            (kyria.thumb_shift_in, kyria.row_offsets.as_str()),
+            (1, "0, 0, 2, 2")
+        );
+        assert_eq!(
+            layout_name("[0] = LAYOUT_split_3x5_2(KC_A, KC_B)"),
+            Some("LAYOUT_split_3x5_2".to_string())
+        );
+        assert_eq!(
+            layout_name("#include QMK_KEYBOARD_H\nconst uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {\n[0] = LAYOUT_planck_grid(KC_A)\n};"),
+            Some("LAYOUT_planck_grid".to_string())
+        );
+        assert_eq!(
+            profile_options(&ops, "[default]\nsplit_space = \"wide\"", "LAYOUT", "")
+                .err()
+                .unwrap()
+                .to_string(),
+            "split_space should be a number"
+        );
+    }
+}
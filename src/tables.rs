@@ -0,0 +1,558 @@
+use easier::prelude::*;
+use pest::iterators::{Pair, Pairs};
+
+use crate::{
+    error::MyError,
+    myparser::{comments, dedent, format_pair, place_comments, RowComments, Rule},
+    options::TableOptions,
+};
+
+/// A C array or variable initialiser outside the keymaps array,
+/// e.g. `combo_t key_combos[] = { ... };`
+pub struct Table {
+    pub declaration: String,
+    pub cast: Option<String>,
+    pub body: TableBody,
+    ///comments above the table, and at the end of its last line
+    pub comments: Vec<String>,
+    pub after: Vec<String>,
+    ///comments between the entries of a list
+    pub row_comments: RowComments,
+}
+pub enum TableBody {
+    Value(Value),
+    List(Vec<Entry>),
+    ///a statement that is not a table, e.g. enum combos { ... };, kept as it is
+    Other(String),
+}
+pub struct Entry {
+    pub index: Option<String>,
+    pub value: Value,
+}
+pub enum Value {
+    Name(String),
+    Call(String, Vec<String>),
+    List(Vec<Value>),
+}
+
+///the tables and other statements, with the comments between them
+pub fn get_tables(pairs: Pairs<Rule>) -> Result<Vec<Table>, MyError> {
+    let mut tables: Vec<Table> = vec![];
+    let mut previous_end = 0;
+    let mut input = "";
+    for pair in pairs {
+        let mut table = match pair.as_rule() {
+            Rule::table => get_table(pair.clone())?,
+            Rule::statement => other(pair.as_str().to_string()),
+            _ => continue,
+        };
+        input = pair.get_input();
+        let start = pair.as_span().start();
+        let (after, above) = split_comments(input, previous_end, start, tables.last().is_some());
+        if let Some(last) = tables.last_mut() {
+            last.after = after;
+        }
+        table.comments = above;
+        previous_end = pair.as_span().end();
+        tables.push(table);
+    }
+    //comments after the last table
+    let (after, rest) = split_comments(input, previous_end, input.len(), true);
+    if let Some(last) = tables.last_mut() {
+        last.after = after;
+    }
+    if !rest.is_empty() {
+        tables.push(other(rest.join("\n")));
+    }
+    Ok(tables)
+}
+
+//the comments in input[start..end], split into those on the line that ends the previous table and the rest
+fn split_comments(
+    input: &str,
+    start: usize,
+    end: usize,
+    previous: bool,
+) -> (Vec<String>, Vec<String>) {
+    let (after, above): (Vec<_>, Vec<_>) = comments(&input[start..end])
+        .into_iter()
+        .map(|(i, comment)| (start + i, comment))
+        .partition(|(i, _)| previous && !input[start..*i].contains('\n'));
+    let dedented = |comments: Vec<(usize, String)>| {
+        comments
+            .into_iter()
+            .map(|(i, comment)| dedent(input, i, &comment))
+            .to_vec()
+    };
+    (dedented(after), dedented(above))
+}
+
+fn other(text: String) -> Table {
+    Table {
+        declaration: String::new(),
+        cast: None,
+        body: TableBody::Other(text),
+        comments: vec![],
+        after: vec![],
+        row_comments: RowComments::new(0),
+    }
+}
+
+///a table on its own, with the comments inside it
+pub fn get_table(pair: Pair<Rule>) -> Result<Table, MyError> {
+    let input = pair.get_input();
+    let (start, end) = (pair.as_span().start(), pair.as_span().end());
+    let mut declaration = String::new();
+    let mut cast = None;
+    let mut body = None;
+    let mut rows = vec![];
+    for inner in pair.into_inner() {
+        match inner.as_rule() {
+            Rule::declaration => {
+                declaration = inner.into_inner().map(|w| w.as_str()).to_vec().join(" ");
+            }
+            Rule::cast => cast = Some(inner.as_str().split_whitespace().to_vec().join(" ")),
+            Rule::tablebody => {
+                rows = inner
+                    .clone()
+                    .into_inner()
+                    .map(|entry| (entry.as_span().start(), entry.as_span().end()))
+                    .to_vec();
+                body = Some(TableBody::List(get_entries(inner)));
+            }
+            Rule::value => body = Some(TableBody::Value(get_value(inner))),
+            _ => {}
+        }
+    }
+    let body = body.ok_or_else(|| format!("No value for {declaration}"))?;
+    let mut table = Table {
+        declaration,
+        cast,
+        body,
+        comments: vec![],
+        after: vec![],
+        row_comments: RowComments::new(rows.len()),
+    };
+    let inside = comments(&input[start..end])
+        .into_iter()
+        .map(|(i, comment)| (start + i, comment))
+        .to_vec();
+    if let Some((_, comment)) = inside.first().filter(|_| table.is_inline()) {
+        return Err(format!(
+            "Can't keep the comment {comment} in {}, which is tidied onto one line",
+            table.declaration
+        )
+        .into());
+    }
+    table.row_comments = place_comments(input, &rows, inside)?;
+    Ok(table)
+}
+
+fn get_entries(pair: Pair<Rule>) -> Vec<Entry> {
+    pair.into_inner()
+        .map(|entry| {
+            let mut index = None;
+            let mut value = Value::List(vec![]);
+            for inner in entry.into_inner() {
+                match inner.as_rule() {
+                    Rule::layernum => index = Some(inner.as_str().to_string()),
+                    Rule::tablebody => {
+                        value =
+                            Value::List(get_entries(inner).into_iter().map(|e| e.value).to_vec())
+                    }
+                    Rule::value => value = get_value(inner),
+                    _ => {}
+                }
+            }
+            Entry { index, value }
+        })
+        .to_vec()
+}
+
+fn get_value(pair: Pair<Rule>) -> Value {
+    let inner = pair.into_inner().next().unwrap();
+    match inner.as_rule() {
+        Rule::reference => Value::Name(inner.as_str().split_whitespace().to_vec().join("")),
+        Rule::key => {
+            let key = inner.into_inner().next().unwrap();
+            match key.as_rule() {
+                Rule::function => {
+                    let mut parts = key.into_inner();
+                    let name = parts.next().unwrap().as_str().to_string();
+                    let params = parts.next().unwrap().into_inner().map(format_pair).to_vec();
+                    Value::Call(name, params)
+                }
+                _ => Value::Name(key.as_str().to_string()),
+            }
+        }
+        _ => Value::Name(inner.as_str().to_string()),
+    }
+}
+
+impl Value {
+    ///cells with their punctuation, so they can be aligned in columns
+    fn cells(&self) -> Vec<String> {
+        match self {
+            Value::Name(name) => vec![name.clone()],
+            Value::Call(name, params) if params.is_empty() => vec![format!("{name}()")],
+            Value::Call(name, params) => {
+                let mut cells = params.iter().map(|p| format!("{p},")).to_vec();
+                cells[0] = format!("{name}({}", cells[0]);
+                let last = cells.last_mut().unwrap();
+                last.pop();
+                last.push(')');
+                cells
+            }
+            Value::List(values) if values.is_empty() => vec!["{}".to_string()],
+            Value::List(values) => {
+                let mut cells = vec![];
+                for (i, value) in values.iter().enumerate() {
+                    let mut vcells = value.cells();
+                    if i < values.len() - 1 {
+                        vcells.last_mut().unwrap().push(',');
+                    }
+                    cells.extend(vcells);
+                }
+                cells[0] = format!("{{ {}", cells[0]);
+                cells.last_mut().unwrap().push_str(" }");
+                cells
+            }
+        }
+    }
+
+    fn is_simple(&self) -> bool {
+        matches!(self, Value::Name(_))
+    }
+}
+
+impl Table {
+    //short lists like combo keys or key override pointers are kept on one line
+    fn is_inline(&self) -> bool {
+        match &self.body {
+            TableBody::Value(_) => true,
+            TableBody::List(entries) => entries
+                .iter()
+                .all(|e| e.index.is_none() && e.value.is_simple()),
+            TableBody::Other(_) => false,
+        }
+    }
+
+    fn is_list(&self) -> bool {
+        matches!(self.body, TableBody::List(_))
+    }
+
+    fn inline_cells(&self) -> Vec<String> {
+        let cast = self.cast.clone().unwrap_or_default();
+        match &self.body {
+            TableBody::Value(value) => {
+                let mut cells = value.cells();
+                cells[0] = format!("{cast}{}", cells[0]);
+                cells.last_mut().unwrap().push(';');
+                cells
+            }
+            TableBody::List(entries) if entries.is_empty() => vec![format!("{cast}{{}};")],
+            TableBody::List(entries) => {
+                let mut cells = entries
+                    .iter()
+                    .map(|e| format!("{},", e.value.cells()[0]))
+                    .to_vec();
+                cells[0] = format!("{cast}{{{}", cells[0]);
+                let last = cells.last_mut().unwrap();
+                last.pop();
+                last.push_str("};");
+                cells
+            }
+            TableBody::Other(text) => vec![text.clone()],
+        }
+    }
+}
+
+pub fn tables_string(tables: &[Table], ops: &TableOptions) -> String {
+    let mut result = String::new();
+    let mut i = 0;
+    while i < tables.len() {
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        for comment in &tables[i].comments {
+            result.push_str(&format!("{comment}\n"));
+        }
+        if tables[i].is_inline() {
+            //consecutive one line tables are aligned together, unless a comment comes between them
+            let mut group = vec![&tables[i]];
+            while ops.align_tables
+                && i + 1 < tables.len()
+                && tables[i + 1].is_inline()
+                && tables[i + 1].is_list() == tables[i].is_list()
+                && tables[i + 1].comments.is_empty()
+            {
+                i += 1;
+                group.push(&tables[i]);
+            }
+            let rows = group
+                .iter()
+                .map(|t| (t.declaration.clone(), t.inline_cells()))
+                .to_vec();
+            for (table, line) in group.iter().zip(layout_rows(&rows, "", " = ", ops)) {
+                push_line(&mut result, &line, &table.after);
+            }
+        } else {
+            result.push_str(&table_string(&tables[i], ops));
+        }
+        i += 1;
+    }
+    result
+}
+
+//a line with the comments that were at the end of it
+fn push_line(result: &mut String, line: &str, after: &[String]) {
+    result.push_str(line);
+    for comment in after {
+        result.push_str(&format!(" {comment}"));
+    }
+    result.push('\n');
+}
+
+fn table_string(table: &Table, ops: &TableOptions) -> String {
+    let mut result = String::new();
+    let entries = match &table.body {
+        TableBody::List(entries) => entries,
+        TableBody::Other(text) => {
+            push_line(&mut result, text, &table.after);
+            return result;
+        }
+        TableBody::Value(_) => return result,
+    };
+    result.push_str(&format!(
+        "{} = {}{{\n",
+        table.declaration,
+        table.cast.clone().unwrap_or_default()
+    ));
+    let rows = entries
+        .iter()
+        .map(|e| {
+            let index = e
+                .index
+                .as_ref()
+                .map(|i| format!("[{i}]"))
+                .unwrap_or_default();
+            let mut cells = e.value.cells();
+            cells.last_mut().unwrap().push(',');
+            (index, cells)
+        })
+        .to_vec();
+    let sep = if rows.iter().any(|r| !r.0.is_empty()) {
+        " = "
+    } else {
+        ""
+    };
+    let indent = " ".repeat(ops.indent);
+    let comments = &table.row_comments;
+    let push_above = |result: &mut String, above: &[String]| {
+        for line in above.iter().flat_map(|c| c.lines()) {
+            result.push_str(&format!("{indent}{line}\n"));
+        }
+    };
+    for (li, line) in layout_rows(&rows, &indent, sep, ops).iter().enumerate() {
+        push_above(&mut result, &comments.above[li]);
+        push_line(&mut result, line, &comments.after[li]);
+    }
+    push_above(&mut result, &comments.above[rows.len()]);
+    push_line(&mut result, "};", &table.after);
+    result
+}
+
+///lay out rows of a prefix and cells as lines, padding each column to the widest in that column
+fn layout_rows(
+    rows: &[(String, Vec<String>)],
+    indent: &str,
+    sep: &str,
+    ops: &TableOptions,
+) -> Vec<String> {
+    let prefix_width = rows
+        .iter()
+        .map(|r| r.0.chars().count())
+        .max()
+        .unwrap_or_default();
+    let column_count = rows.iter().map(|r| r.1.len()).max().unwrap_or_default();
+    let widths = (0..column_count)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|r| r.1.get(i))
+                .map(|c| c.chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .to_vec();
+
+    let mut lines = vec![];
+    for (prefix, cells) in rows {
+        let mut result = indent.to_string();
+        if ops.align_columns {
+            result.push_str(&format!("{prefix: <prefix_width$}{sep}"));
+        } else {
+            result.push_str(&format!("{prefix}{sep}"));
+        }
+        for (i, cell) in cells.iter().enumerate() {
+            if i == cells.len() - 1 {
+                result.push_str(cell);
+            } else if ops.align_columns {
+                result.push_str(&format!("{: <1$}", cell, widths[i] + 1));
+            } else {
+                result.push_str(cell);
+                result.push(' ');
+            }
+        }
+        lines.push(result);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use pest::Parser;
+
+    use super::*;
+    use crate::myparser::MyParser;
+
+    fn tidy(example: &str) -> String {
+        let pairs = MyParser::parse(Rule::tablesouter, example).unwrap();
+        let tables = get_tables(pairs).unwrap();
+        tables_string(&tables, &TableOptions::default())
+    }
+
+    #[test]
+    fn tap_dance() {
+        let example = r#"
+        tap_dance_action_t tap_dance_actions[] = {
+            [TD_ESC_CAPS]=ACTION_TAP_DANCE_DOUBLE(KC_ESC,KC_CAPS),
+               [TD_L] = ACTION_TAP_DANCE_LAYER_MOVE(KC_A, 2)
+        };
+        "#;
+        assert_eq!(
+            tidy(example),
+            r#"tap_dance_action_t tap_dance_actions[] = {
+    [TD_ESC_CAPS] = ACTION_TAP_DANCE_DOUBLE(KC_ESC,   KC_CAPS),
+    [TD_L]        = ACTION_TAP_DANCE_LAYER_MOVE(KC_A, 2),
+};
+"#
+        );
+    }
+
+    #[test]
+    fn combos() {
+        let example = r#"
+        const uint16_t PROGMEM esc_combo[] = {KC_E, KC_W, COMBO_END};
+        const uint16_t PROGMEM shift_combo[] = {KC_LSFT,KC_A, COMBO_END};
+        combo_t key_combos[] = {
+            COMBO(esc_combo, KC_ESC),
+            COMBO(shift_combo, LSFT(KC_A)),
+        };
+        "#;
+        assert_eq!(
+            tidy(example),
+            r#"const uint16_t PROGMEM esc_combo[]   = {KC_E,    KC_W, COMBO_END};
+const uint16_t PROGMEM shift_combo[] = {KC_LSFT, KC_A, COMBO_END};
+
+combo_t key_combos[] = {
+    COMBO(esc_combo,   KC_ESC),
+    COMBO(shift_combo, LSFT(KC_A)),
+};
+"#
+        );
+    }
+
+    #[test]
+    fn comments() {
+        let example = r#"
+        // combos
+        const uint16_t PROGMEM esc_combo[] = {KC_E, KC_W, COMBO_END}; // e and w
+        combo_t key_combos[] = {
+            // escape
+            COMBO(esc_combo, KC_ESC),
+            COMBO(shift_combo, LSFT(KC_A)), /* shift */
+            /* more,
+               later */
+        };
+        // trailing
+        "#;
+        assert_eq!(
+            tidy(example),
+            r#"// combos
+const uint16_t PROGMEM esc_combo[] = {KC_E, KC_W, COMBO_END}; // e and w
+
+combo_t key_combos[] = {
+    // escape
+    COMBO(esc_combo,   KC_ESC),
+    COMBO(shift_combo, LSFT(KC_A)), /* shift */
+    /* more,
+       later */
+};
+
+// trailing
+"#
+        );
+        let inline = "const uint16_t PROGMEM esc_combo[] = {KC_E, /* e */ KC_W, COMBO_END};";
+        let pairs = MyParser::parse(Rule::tablesouter, inline).unwrap();
+        assert_eq!(
+            get_tables(pairs).err().unwrap().to_string(),
+            "Can't keep the comment /* e */ in const uint16_t PROGMEM esc_combo[], which is tidied onto one line"
+        );
+    }
+
+    #[test]
+    fn statements() {
+        let example = r#"
+        #include QMK_KEYBOARD_H
+        enum combos { ESC_COMBO, /* ; */ COMBO_LENGTH };
+        uint16_t COMBO_LEN = COMBO_LENGTH;
+        const uint16_t PROGMEM esc_combo[] = {KC_E,KC_W,COMBO_END};
+        bool get_combo_must_tap(uint16_t index, combo_t *combo) { return true; }
+        combo_t key_combos[] = {COMBO(esc_combo,KC_ESC)};
+        "#;
+        assert_eq!(
+            tidy(example),
+            r#"#include QMK_KEYBOARD_H
+
+enum combos { ESC_COMBO, /* ; */ COMBO_LENGTH };
+
+uint16_t COMBO_LEN = COMBO_LENGTH;
+
+const uint16_t PROGMEM esc_combo[] = {KC_E, KC_W, COMBO_END};
+
+bool get_combo_must_tap(uint16_t index, combo_t *combo) { return true; }
+
+combo_t key_combos[] = {
+    COMBO(esc_combo, KC_ESC),
+};
+"#
+        );
+    }
+
+    #[test]
+    fn key_overrides_and_encoders() {
+        let example = r#"
+        const key_override_t delete_key_override = ko_make_basic(MOD_MASK_SHIFT, KC_BSPC, KC_DEL);
+        const key_override_t **key_overrides = (const key_override_t *[]){
+            &delete_key_override,
+            NULL
+        };
+        const uint16_t PROGMEM encoder_map[][NUM_ENCODERS][NUM_DIRECTIONS] = {
+            [0] = { ENCODER_CCW_CW(KC_VOLD, KC_VOLU), ENCODER_CCW_CW(KC_PGDN,KC_PGUP) },
+            [_RAISE] = { ENCODER_CCW_CW(RGB_HUD, RGB_HUI), ENCODER_CCW_CW(_______, _______) },
+        };
+        "#;
+        assert_eq!(
+            tidy(example),
+            r#"const key_override_t delete_key_override = ko_make_basic(MOD_MASK_SHIFT, KC_BSPC, KC_DEL);
+
+const key_override_t **key_overrides = (const key_override_t *[]){&delete_key_override, NULL};
+
+const uint16_t PROGMEM encoder_map[][NUM_ENCODERS][NUM_DIRECTIONS] = {
+    [0]      = { ENCODER_CCW_CW(KC_VOLD, KC_VOLU), ENCODER_CCW_CW(KC_PGDN, KC_PGUP) },
+    [_RAISE] = { ENCODER_CCW_CW(RGB_HUD, RGB_HUI), ENCODER_CCW_CW(_______, _______) },
+};
+"#
+        );
+    }
+}
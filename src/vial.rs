@@ -0,0 +1,539 @@
+use easier::prelude::*;
+use pest::{iterators::Pair, Parser};
+
+use crate::{
+    encoders::Encoder,
+    error::MyError,
+    myparser::{Keymap, Layer, MyParser, Rule},
+    options::PrintOptions,
+    preprocessor::Item,
+};
+
+enum Json {
+    Object(Vec<(String, Json)>),
+    Array(Vec<Json>),
+    String(String),
+    Number(String),
+    Literal,
+}
+
+impl Json {
+    fn from_pair(pair: Pair<Rule>) -> Json {
+        match pair.as_rule() {
+            Rule::jobject => Json::Object(
+                pair.into_inner()
+                    .map(|p| {
+                        let mut inner = p.into_inner();
+                        let key = string_text(inner.next().unwrap());
+                        (key, Json::from_pair(inner.next().unwrap()))
+                    })
+                    .to_vec(),
+            ),
+            Rule::jarray => Json::Array(pair.into_inner().map(Json::from_pair).to_vec()),
+            Rule::string => Json::String(string_text(pair)),
+            Rule::jnumber => Json::Number(pair.as_str().to_string()),
+            _ => Json::Literal,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(pairs) => pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn items(&self) -> &[Json] {
+        match self {
+            Json::Array(items) => items,
+            _ => &[],
+        }
+    }
+}
+
+fn string_text(pair: Pair<Rule>) -> String {
+    pair.into_inner().next().unwrap().as_str().to_string()
+}
+
+//basic keycodes that aren't letters, numbers or F keys, by number
+const BASIC: &[(u16, &str)] = &[
+    (0x00, "KC_NO"),
+    (0x01, "KC_TRNS"),
+    (0x28, "KC_ENT"),
+    (0x29, "KC_ESC"),
+    (0x2A, "KC_BSPC"),
+    (0x2B, "KC_TAB"),
+    (0x2C, "KC_SPC"),
+    (0x2D, "KC_MINS"),
+    (0x2E, "KC_EQL"),
+    (0x2F, "KC_LBRC"),
+    (0x30, "KC_RBRC"),
+    (0x31, "KC_BSLS"),
+    (0x32, "KC_NUHS"),
+    (0x33, "KC_SCLN"),
+    (0x34, "KC_QUOT"),
+    (0x35, "KC_GRV"),
+    (0x36, "KC_COMM"),
+    (0x37, "KC_DOT"),
+    (0x38, "KC_SLSH"),
+    (0x39, "KC_CAPS"),
+    (0x46, "KC_PSCR"),
+    (0x47, "KC_SCRL"),
+    (0x48, "KC_PAUS"),
+    (0x49, "KC_INS"),
+    (0x4A, "KC_HOME"),
+    (0x4B, "KC_PGUP"),
+    (0x4C, "KC_DEL"),
+    (0x4D, "KC_END"),
+    (0x4E, "KC_PGDN"),
+    (0x4F, "KC_RGHT"),
+    (0x50, "KC_LEFT"),
+    (0x51, "KC_DOWN"),
+    (0x52, "KC_UP"),
+    (0x53, "KC_NUM"),
+    (0x54, "KC_PSLS"),
+    (0x55, "KC_PAST"),
+    (0x56, "KC_PMNS"),
+    (0x57, "KC_PPLS"),
+    (0x58, "KC_PENT"),
+    (0x62, "KC_P0"),
+    (0x63, "KC_PDOT"),
+    (0x64, "KC_NUBS"),
+    (0x65, "KC_APP"),
+    (0xA8, "KC_MUTE"),
+    (0xA9, "KC_VOLU"),
+    (0xAA, "KC_VOLD"),
+    (0xAB, "KC_MNXT"),
+    (0xAC, "KC_MPRV"),
+    (0xAD, "KC_MSTP"),
+    (0xAE, "KC_MPLY"),
+    (0xE0, "KC_LCTL"),
+    (0xE1, "KC_LSFT"),
+    (0xE2, "KC_LALT"),
+    (0xE3, "KC_LGUI"),
+    (0xE4, "KC_RCTL"),
+    (0xE5, "KC_RSFT"),
+    (0xE6, "KC_RALT"),
+    (0xE7, "KC_RGUI"),
+];
+
+//quantum keycodes without parameters
+const QUANTUM: &[(u16, &str)] = &[
+    (0x7C00, "QK_BOOT"),
+    (0x7C01, "QK_RBT"),
+    (0x7C02, "QK_DEBUG_TOGGLE"),
+    (0x7C03, "QK_CLEAR_EEPROM"),
+    (0x7C73, "CW_TOGG"),
+    (0x7C79, "QK_REP"),
+    (0x7C7A, "QK_AREP"),
+];
+
+//layer functions with the layer in the low 5 bits, by their first number
+const LAYER_FUNCTIONS: &[(u16, &str)] = &[
+    (0x5200, "TO"),
+    (0x5220, "MO"),
+    (0x5240, "DF"),
+    (0x5260, "TG"),
+    (0x5280, "OSL"),
+    (0x52C0, "TT"),
+];
+
+///read the layers of a Vial .vil or VIA .json backup,
+///with a warning for each keycode we can't read, which is left as KC_NO.
+///backups are in switch matrix order, so info is the keyboard's info.json (or keyboard.json),
+///whose first layout gives the matrix position of each LAYOUT argument
+pub fn get_vial_keymap(
+    json: &str,
+    info: &str,
+    ops: &PrintOptions,
+) -> Result<(Keymap, Vec<String>), MyError> {
+    let pair = MyParser::parse(Rule::jsonouter, json)?.next().unwrap();
+    let json = Json::from_pair(pair);
+    if info.trim().is_empty() {
+        return Err(
+            "Backups are in switch matrix order, add the keyboard's info.json to put keys in LAYOUT order"
+                .to_string()
+                .into(),
+        );
+    }
+    let pair = MyParser::parse(Rule::jsonouter, info)?.next().unwrap();
+    let info = Json::from_pair(pair);
+    let (name, positions) = layout_positions(&info)?;
+    let mut warnings = vec![];
+    //Vial: layout[layer][row][col], VIA: layers[layer][row * cols + col]
+    let layers = if let Some(layout) = json.get("layout") {
+        layout
+            .items()
+            .iter()
+            .map(|layer| {
+                positions
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|(r, c)| layer.items().get(*r).and_then(|row| row.items().get(*c)))
+                            .to_vec()
+                    })
+                    .to_vec()
+            })
+            .to_vec()
+    } else if let Some(layers) = json.get("layers") {
+        //the matrix size, or just wide enough for the layout
+        let cols = info
+            .get("matrix_size")
+            .and_then(|size| size.get("cols"))
+            .and_then(number)
+            .unwrap_or_else(|| {
+                positions
+                    .iter()
+                    .flatten()
+                    .map(|(_, c)| c + 1)
+                    .max()
+                    .unwrap_or(0)
+            });
+        layers
+            .items()
+            .iter()
+            .map(|layer| {
+                positions
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|(r, c)| layer.items().get(r * cols + c))
+                            .to_vec()
+                    })
+                    .to_vec()
+            })
+            .to_vec()
+    } else {
+        return Err("No layout or layers found".to_string().into());
+    };
+
+    let mut layer_codes = vec![];
+    for (layi, layer) in layers.iter().enumerate() {
+        let rows = layer
+            .iter()
+            .enumerate()
+            .map(|(rowi, row)| {
+                row.iter()
+                    .zip(&positions[rowi])
+                    .map(|(key, (r, c))| match key.map(keycode) {
+                        Some(Ok(Some(code))) => Ok(code),
+                        Some(Err(unknown)) => {
+                            warnings.push(format!("Layer {layi}: unknown keycode {unknown}"));
+                            Ok("KC_NO".to_string())
+                        }
+                        _ => Err(format!("Layer {layi}: no key at matrix [{r}, {c}]")),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        layer_codes.push(rows);
+    }
+    //backups have every dynamic layer, even ones that aren't used
+    while layer_codes.len() > 1
+        && layer_codes.last().is_some_and(|rows| {
+            rows.iter()
+                .flatten()
+                .all(|c| c == "KC_TRNS" || c == "KC_NO")
+        })
+    {
+        layer_codes.pop();
+    }
+
+    let encoder_layers = json
+        .get("encoder_layout")
+        .map(|e| e.items().iter().to_vec())
+        .unwrap_or_default();
+    let split = ops.board.is_split(&name);
+    let mut items = vec![];
+    for (layi, rows) in layer_codes.into_iter().enumerate() {
+        let rows = rows.into_iter().map(Item::Item).to_vec();
+        let mut layer = Layer::new(layi.to_string(), name.clone(), rows, split, ops)?;
+        if let Some(encoders) = encoder_layers.get(layi) {
+            for encoder in encoders.items() {
+                let [ccw, cw] = encoder.items() else {
+                    continue;
+                };
+                let (Ok(Some(ccw)), Ok(Some(cw))) = (keycode(ccw), keycode(cw)) else {
+                    continue;
+                };
+                layer.encoders.push(Encoder { ccw, cw });
+            }
+        }
+        items.push(Item::Item(layer));
+    }
+    Ok((Keymap::new(items, ops)?, warnings))
+}
+
+//matrix row and column
+type Position = (usize, usize);
+
+//the LAYOUT name and the matrix [row, col] of each key, in rows that start when the keys go back to the left
+fn layout_positions(info: &Json) -> Result<(String, Vec<Vec<Position>>), MyError> {
+    let Some(Json::Object(layouts)) = info.get("layouts") else {
+        return Err("No layouts found in info.json".to_string().into());
+    };
+    let Some((name, layout)) = layouts.first() else {
+        return Err("No layouts found in info.json".to_string().into());
+    };
+    let mut rows: Vec<Vec<Position>> = vec![];
+    let mut last_x = f64::MAX;
+    for key in layout.get("layout").map(Json::items).unwrap_or_default() {
+        let matrix = key.get("matrix").map(Json::items).unwrap_or_default();
+        let (Some(r), Some(c)) = (
+            matrix.first().and_then(number),
+            matrix.get(1).and_then(number),
+        ) else {
+            return Err(format!("{name}: each key needs a matrix position").into());
+        };
+        let x = match key.get("x") {
+            Some(Json::Number(x)) => x.parse::<f64>().unwrap_or(0.0),
+            _ => 0.0,
+        };
+        if x <= last_x {
+            rows.push(vec![]);
+        }
+        last_x = x;
+        rows.last_mut().unwrap().push((r, c));
+    }
+    if rows.is_empty() {
+        return Err(format!("{name} has no keys").into());
+    }
+    Ok((name.clone(), rows))
+}
+
+fn number(json: &Json) -> Option<usize> {
+    match json {
+        Json::Number(n) => n.parse().ok(),
+        _ => None,
+    }
+}
+
+//the keycode name, None for matrix positions without a key, or what we couldn't read
+fn keycode(key: &Json) -> Result<Option<String>, String> {
+    match key {
+        Json::Number(n) if n == "-1" => Ok(None),
+        Json::Number(n) => {
+            let code = n.parse::<u16>().map_err(|_| n.clone())?;
+            keycode_name(code)
+                .map(Some)
+                .ok_or_else(|| format!("0x{code:04X}"))
+        }
+        //Vial writes numbers it doesn't have a name for as hex strings
+        Json::String(s) if s.starts_with("0x") => {
+            let code = u16::from_str_radix(&s[2..], 16).map_err(|_| s.clone())?;
+            keycode_name(code).map(Some).ok_or_else(|| s.clone())
+        }
+        Json::String(s) => {
+            //names can't have spaces in them, which the keymap grammar would skip
+            let spaced = s.split(',').any(|p| p.trim().contains(char::is_whitespace));
+            match MyParser::parse(Rule::key, s) {
+                Ok(pairs) if pairs.as_str() == s && !spaced => Ok(Some(s.clone())),
+                _ => Err(s.clone()),
+            }
+        }
+        _ => Err("an unexpected value".to_string()),
+    }
+}
+
+///the name of a numeric keycode, using QMK's current numbering
+pub fn keycode_name(code: u16) -> Option<String> {
+    let low = code & 0xFF;
+    match code {
+        0x0000..=0x00FF => basic_name(code),
+        //modifiers and a key, e.g. LSFT(KC_A)
+        0x0100..=0x1FFF => {
+            let mut name = basic_name(low)?;
+            for modifier in mod_names((code >> 8) & 0x1F).into_iter().rev() {
+                name = format!("{modifier}({name})");
+            }
+            Some(name)
+        }
+        //mod tap, e.g. LSFT_T(KC_A)
+        0x2000..=0x3FFF => {
+            let mods = mod_names((code >> 8) & 0x1F);
+            let key = basic_name(low)?;
+            match mods.as_slice() {
+                [modifier] => Some(format!("{modifier}_T({key})")),
+                _ => Some(format!(
+                    "MT({},{key})",
+                    mods.iter().map(|m| format!("MOD_{m}")).to_vec().join("|")
+                )),
+            }
+        }
+        0x4000..=0x4FFF => Some(format!("LT({},{})", (code >> 8) & 0xF, basic_name(low)?)),
+        0x5000..=0x51FF => {
+            let mods = mod_names(code & 0x1F)
+                .iter()
+                .map(|m| format!("MOD_{m}"))
+                .to_vec();
+            Some(format!("LM({},{})", (code >> 5) & 0xF, mods.join("|")))
+        }
+        0x52A0..=0x52BF => {
+            let mods = mod_names(code & 0x1F)
+                .iter()
+                .map(|m| format!("MOD_{m}"))
+                .to_vec();
+            Some(format!("OSM({})", mods.join("|")))
+        }
+        0x5200..=0x52DF => {
+            let (start, name) = LAYER_FUNCTIONS
+                .iter()
+                .find(|(start, _)| (*start..*start + 0x20).contains(&code))?;
+            Some(format!("{name}({})", code - start))
+        }
+        0x5700..=0x57FF => Some(format!("TD({low})")),
+        0x7E00..=0x7E3F => Some(format!("QK_KB_{}", code - 0x7E00)),
+        0x7E40..=0x7FFF => Some(format!("QK_USER_{}", code - 0x7E40)),
+        _ => QUANTUM
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, name)| name.to_string()),
+    }
+}
+
+//e.g. 0x04 to KC_A, 0x1E to KC_1, 0x3A to KC_F1
+fn basic_name(code: u16) -> Option<String> {
+    match code {
+        0x04..=0x1D => Some(format!("KC_{}", (b'A' + (code - 0x04) as u8) as char)),
+        0x1E..=0x26 => Some(format!("KC_{}", code - 0x1D)),
+        0x27 => Some("KC_0".to_string()),
+        0x3A..=0x45 => Some(format!("KC_F{}", code - 0x39)),
+        0x59..=0x61 => Some(format!("KC_P{}", code - 0x58)),
+        0x68..=0x73 => Some(format!("KC_F{}", code - 0x5B)),
+        _ => BASIC
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, name)| name.to_string()),
+    }
+}
+
+//5 bit modifiers, ctrl, shift, alt, gui and a bit for right hand
+fn mod_names(mods: u16) -> Vec<String> {
+    let side = if mods & 0x10 != 0 { "R" } else { "L" };
+    [(0x01, "CTL"), (0x02, "SFT"), (0x04, "ALT"), (0x08, "GUI")]
+        .iter()
+        .filter(|(bit, _)| mods & bit != 0)
+        .map(|(_, name)| format!("{side}{name}"))
+        .to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_keycodes() {
+        assert_eq!(keycode_name(0x04).unwrap(), "KC_A");
+        assert_eq!(keycode_name(0x27).unwrap(), "KC_0");
+        assert_eq!(keycode_name(0x0204).unwrap(), "LSFT(KC_A)");
+        assert_eq!(keycode_name(0x2204).unwrap(), "LSFT_T(KC_A)");
+        assert_eq!(keycode_name(0x2304).unwrap(), "MT(MOD_LCTL|MOD_LSFT,KC_A)");
+        assert_eq!(keycode_name(0x412C).unwrap(), "LT(1,KC_SPC)");
+        assert_eq!(keycode_name(0x5221).unwrap(), "MO(1)");
+        assert_eq!(keycode_name(0x5262).unwrap(), "TG(2)");
+        assert_eq!(keycode_name(0x52A2).unwrap(), "OSM(MOD_LSFT)");
+        assert_eq!(keycode_name(0x7C00).unwrap(), "QK_BOOT");
+    }
+
+    #[test]
+    fn vial_backup() {
+        let vil = r#"{"version": 1, "layout": [
+            [["KC_Q", "KC_W", -1], ["LSFT_T(KC_A)", 16684, "0x5221"]],
+            [["KC_TRNS", "KC_1", -1], ["KC_NO", "BAD KEY", 4]],
+            [["KC_TRNS", "KC_TRNS", -1], ["KC_TRNS", "KC_TRNS", "KC_TRNS"]]
+        ], "encoder_layout": [[["KC_VOLD", "KC_VOLU"]], [["KC_LEFT", "KC_RGHT"]]]}"#;
+        let info = r#"{"layouts": {"LAYOUT": {"layout": [
+            {"matrix": [0, 0], "x": 0, "y": 0}, {"matrix": [0, 1], "x": 1, "y": 0},
+            {"matrix": [1, 0], "x": 0, "y": 1}, {"matrix": [1, 1], "x": 1, "y": 1},
+            {"matrix": [1, 2], "x": 2, "y": 1}
+        ]}}}"#;
+        let ops = PrintOptions {
+            thumb_shift_in: 0,
+            ..Default::default()
+        };
+        let (keymap, warnings) = get_vial_keymap(vil, info, &ops).unwrap();
+        assert_eq!(keymap.layers.len(), 2);
+        let keys = keymap.layers[0].active_keys();
+        assert_eq!(keys[1][1].as_deref(), Some("LT(1,KC_SPC)"));
+        assert_eq!(keys[1][2].as_deref(), Some("MO(1)"));
+        assert_eq!(keymap.layers[1].encoders[0].cw, "KC_RGHT");
+        assert_eq!(warnings, vec!["Layer 1: unknown keycode BAD KEY"]);
+        assert!(get_vial_keymap(vil, "", &ops).is_err());
+
+        let via =
+            r#"{"name": "test", "layers": [["KC_A", "KC_B", "KC_C", "KC_D", "KC_E", "KC_F"]]}"#;
+        let info = r#"{"matrix_size": {"rows": 2, "cols": 3}, "layouts": {"LAYOUT": {"layout": [
+            {"matrix": [0, 0], "x": 0}, {"matrix": [0, 1], "x": 1}, {"matrix": [1, 0], "x": 0}
+        ]}}}"#;
+        let (keymap, _) = get_vial_keymap(via, info, &ops).unwrap();
+        assert_eq!(
+            keymap.layers[0].active_keys()[1][0].as_deref(),
+            Some("KC_D")
+        );
+    }
+
+    #[test]
+    fn split_matrix() {
+        //a corne: the left half is matrix rows 0-3, the right half rows 4-7 with its columns mirrored
+        let vil = r#"{"version": 1, "layout": [[
+            ["KC_TAB", "KC_Q", "KC_W", "KC_E", "KC_R", "KC_T"],
+            ["KC_LCTL", "KC_A", "KC_S", "KC_D", "KC_F", "KC_G"],
+            ["KC_LSFT", "KC_Z", "KC_X", "KC_C", "KC_V", "KC_B"],
+            [-1, -1, -1, "KC_LGUI", "MO(1)", "KC_SPC"],
+            ["KC_BSPC", "KC_P", "KC_O", "KC_I", "KC_U", "KC_Y"],
+            ["KC_QUOT", "KC_SCLN", "KC_L", "KC_K", "KC_J", "KC_H"],
+            ["KC_ESC", "KC_SLSH", "KC_DOT", "KC_COMM", "KC_M", "KC_N"],
+            [-1, -1, -1, "KC_RALT", "MO(2)", "KC_ENT"]
+        ]]}"#;
+        let mut keys = vec![];
+        for row in 0..3 {
+            for col in 0..6 {
+                keys.push(format!(
+                    r#"{{"matrix": [{row}, {col}], "x": {col}, "y": {row}}}"#
+                ));
+            }
+            for col in 0..6 {
+                keys.push(format!(
+                    r#"{{"matrix": [{}, {}], "x": {}, "y": {row}}}"#,
+                    row + 4,
+                    5 - col,
+                    col + 9
+                ));
+            }
+        }
+        for (r, c, x) in [
+            (3, 3, 3.5),
+            (3, 4, 4.5),
+            (3, 5, 5.5),
+            (7, 5, 8.5),
+            (7, 4, 9.5),
+            (7, 3, 10.5),
+        ] {
+            keys.push(format!(r#"{{"matrix": [{r}, {c}], "x": {x}, "y": 3.7}}"#));
+        }
+        let info = format!(
+            r#"{{"matrix_size": {{"rows": 8, "cols": 6}}, "layouts": {{"LAYOUT_split_3x6_3": {{"layout": [{}]}}}}}}"#,
+            keys.join(", ")
+        );
+        let ops = PrintOptions::default();
+        let (keymap, warnings) = get_vial_keymap(vil, &info, &ops).unwrap();
+        assert!(warnings.is_empty());
+        let layer = &keymap.layers[0];
+        assert_eq!(layer.name(), "LAYOUT_split_3x6_3");
+        let keys = layer
+            .keys
+            .iter()
+            .map(|row| row.iter().flatten().map(|k| k.as_str()).to_vec().join(" "))
+            .to_vec();
+        assert_eq!(
+            keys,
+            vec![
+                "KC_TAB KC_Q KC_W KC_E KC_R KC_T KC_Y KC_U KC_I KC_O KC_P KC_BSPC",
+                "KC_LCTL KC_A KC_S KC_D KC_F KC_G KC_H KC_J KC_K KC_L KC_SCLN KC_QUOT",
+                "KC_LSFT KC_Z KC_X KC_C KC_V KC_B KC_N KC_M KC_COMM KC_DOT KC_SLSH KC_ESC",
+                "KC_LGUI MO(1) KC_SPC KC_ENT MO(2) KC_RALT",
+            ]
+        );
+    }
+}
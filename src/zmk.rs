@@ -0,0 +1,668 @@
+use easier::prelude::*;
+use pest::{iterators::Pair, Parser};
+
+use crate::{
+    context::Context,
+    error::MyError,
+    key::{short_keycode, split_call},
+    myparser::{comments, layers_string, Keymap, Layer, MyParser, Rule},
+    options::PrintOptions,
+    preprocessor::Item,
+};
+
+//ZMK key names and their QMK keycodes, letters, numbers and F keys are worked out in code
+const KEYS: &[(&str, &str)] = &[
+    ("RET", "KC_ENT"),
+    ("RETURN", "KC_ENT"),
+    ("ENTER", "KC_ENT"),
+    ("ESC", "KC_ESC"),
+    ("ESCAPE", "KC_ESC"),
+    ("BSPC", "KC_BSPC"),
+    ("BACKSPACE", "KC_BSPC"),
+    ("TAB", "KC_TAB"),
+    ("SPACE", "KC_SPC"),
+    ("SPC", "KC_SPC"),
+    ("MINUS", "KC_MINS"),
+    ("EQUAL", "KC_EQL"),
+    ("LBKT", "KC_LBRC"),
+    ("LEFT_BRACKET", "KC_LBRC"),
+    ("RBKT", "KC_RBRC"),
+    ("RIGHT_BRACKET", "KC_RBRC"),
+    ("BSLH", "KC_BSLS"),
+    ("BACKSLASH", "KC_BSLS"),
+    ("SEMI", "KC_SCLN"),
+    ("SEMICOLON", "KC_SCLN"),
+    ("SQT", "KC_QUOT"),
+    ("APOS", "KC_QUOT"),
+    ("SINGLE_QUOTE", "KC_QUOT"),
+    ("APOSTROPHE", "KC_QUOT"),
+    ("GRAVE", "KC_GRV"),
+    ("COMMA", "KC_COMM"),
+    ("DOT", "KC_DOT"),
+    ("PERIOD", "KC_DOT"),
+    ("FSLH", "KC_SLSH"),
+    ("SLASH", "KC_SLSH"),
+    ("CAPS", "KC_CAPS"),
+    ("CAPSLOCK", "KC_CAPS"),
+    ("CLCK", "KC_CAPS"),
+    ("PSCRN", "KC_PSCR"),
+    ("PRINTSCREEN", "KC_PSCR"),
+    ("SLCK", "KC_SCRL"),
+    ("SCROLLLOCK", "KC_SCRL"),
+    ("PAUSE_BREAK", "KC_PAUS"),
+    ("INS", "KC_INS"),
+    ("INSERT", "KC_INS"),
+    ("HOME", "KC_HOME"),
+    ("END", "KC_END"),
+    ("PG_UP", "KC_PGUP"),
+    ("PAGE_UP", "KC_PGUP"),
+    ("PG_DN", "KC_PGDN"),
+    ("PAGE_DOWN", "KC_PGDN"),
+    ("DEL", "KC_DEL"),
+    ("DELETE", "KC_DEL"),
+    ("RIGHT", "KC_RGHT"),
+    ("RIGHT_ARROW", "KC_RGHT"),
+    ("LEFT", "KC_LEFT"),
+    ("LEFT_ARROW", "KC_LEFT"),
+    ("DOWN", "KC_DOWN"),
+    ("DOWN_ARROW", "KC_DOWN"),
+    ("UP", "KC_UP"),
+    ("UP_ARROW", "KC_UP"),
+    ("K_APP", "KC_APP"),
+    ("K_APPLICATION", "KC_APP"),
+    //modifiers
+    ("LSHFT", "KC_LSFT"),
+    ("LSHIFT", "KC_LSFT"),
+    ("LEFT_SHIFT", "KC_LSFT"),
+    ("LCTRL", "KC_LCTL"),
+    ("LEFT_CONTROL", "KC_LCTL"),
+    ("LALT", "KC_LALT"),
+    ("LEFT_ALT", "KC_LALT"),
+    ("LGUI", "KC_LGUI"),
+    ("LCMD", "KC_LGUI"),
+    ("LWIN", "KC_LGUI"),
+    ("LMETA", "KC_LGUI"),
+    ("LEFT_GUI", "KC_LGUI"),
+    ("RSHFT", "KC_RSFT"),
+    ("RSHIFT", "KC_RSFT"),
+    ("RIGHT_SHIFT", "KC_RSFT"),
+    ("RCTRL", "KC_RCTL"),
+    ("RIGHT_CONTROL", "KC_RCTL"),
+    ("RALT", "KC_RALT"),
+    ("RIGHT_ALT", "KC_RALT"),
+    ("RGUI", "KC_RGUI"),
+    ("RCMD", "KC_RGUI"),
+    ("RWIN", "KC_RGUI"),
+    ("RMETA", "KC_RGUI"),
+    ("RIGHT_GUI", "KC_RGUI"),
+    //shifted symbols
+    ("EXCL", "KC_EXLM"),
+    ("EXCLAMATION", "KC_EXLM"),
+    ("AT", "KC_AT"),
+    ("AT_SIGN", "KC_AT"),
+    ("HASH", "KC_HASH"),
+    ("POUND", "KC_HASH"),
+    ("DLLR", "KC_DLR"),
+    ("DOLLAR", "KC_DLR"),
+    ("PRCNT", "KC_PERC"),
+    ("PERCENT", "KC_PERC"),
+    ("CARET", "KC_CIRC"),
+    ("AMPS", "KC_AMPR"),
+    ("AMPERSAND", "KC_AMPR"),
+    ("STAR", "KC_ASTR"),
+    ("ASTRK", "KC_ASTR"),
+    ("ASTERISK", "KC_ASTR"),
+    ("LPAR", "KC_LPRN"),
+    ("LEFT_PARENTHESIS", "KC_LPRN"),
+    ("RPAR", "KC_RPRN"),
+    ("RIGHT_PARENTHESIS", "KC_RPRN"),
+    ("LBRC", "KC_LCBR"),
+    ("LEFT_BRACE", "KC_LCBR"),
+    ("RBRC", "KC_RCBR"),
+    ("RIGHT_BRACE", "KC_RCBR"),
+    ("PIPE", "KC_PIPE"),
+    ("TILDE", "KC_TILD"),
+    ("UNDER", "KC_UNDS"),
+    ("UNDERSCORE", "KC_UNDS"),
+    ("PLUS", "KC_PLUS"),
+    ("COLON", "KC_COLN"),
+    ("DQT", "KC_DQUO"),
+    ("DOUBLE_QUOTES", "KC_DQUO"),
+    ("LT", "KC_LT"),
+    ("LESS_THAN", "KC_LT"),
+    ("GT", "KC_GT"),
+    ("GREATER_THAN", "KC_GT"),
+    ("QMARK", "KC_QUES"),
+    ("QUESTION", "KC_QUES"),
+    //media
+    ("C_VOL_UP", "KC_VOLU"),
+    ("C_VOLUME_UP", "KC_VOLU"),
+    ("C_VOL_DN", "KC_VOLD"),
+    ("C_VOLUME_DOWN", "KC_VOLD"),
+    ("C_MUTE", "KC_MUTE"),
+    ("C_PP", "KC_MPLY"),
+    ("C_PLAY_PAUSE", "KC_MPLY"),
+    ("C_NEXT", "KC_MNXT"),
+    ("C_PREV", "KC_MPRV"),
+    ("C_STOP", "KC_MSTP"),
+    ("C_BRI_UP", "KC_BRIU"),
+    ("C_BRI_DN", "KC_BRID"),
+    //keypad
+    ("KP_PLUS", "KC_PPLS"),
+    ("KP_MINUS", "KC_PMNS"),
+    ("KP_MULTIPLY", "KC_PAST"),
+    ("KP_DIVIDE", "KC_PSLS"),
+    ("KP_DOT", "KC_PDOT"),
+    ("KP_ENTER", "KC_PENT"),
+    ("KP_EQUAL", "KC_PEQL"),
+    ("KP_NUMLOCK", "KC_NUM"),
+];
+
+//modifier functions e.g. LS(A) is LSFT(KC_A)
+const MOD_FUNCTIONS: &[(&str, &str)] = &[
+    ("LS", "LSFT"),
+    ("LC", "LCTL"),
+    ("LA", "LALT"),
+    ("LG", "LGUI"),
+    ("RS", "RSFT"),
+    ("RC", "RCTL"),
+    ("RA", "RALT"),
+    ("RG", "RGUI"),
+];
+
+//behaviours that take no parameters
+const SIMPLE: &[(&str, &str)] = &[
+    ("&trans", "KC_TRNS"),
+    ("&none", "KC_NO"),
+    ("&bootloader", "QK_BOOT"),
+    ("&sys_reset", "QK_RBT"),
+    ("&caps_word", "CW_TOGG"),
+    ("&key_repeat", "QK_REP"),
+];
+
+//behaviours that take a layer
+const LAYER_BEHAVIOURS: &[(&str, &str)] =
+    &[("&mo", "MO"), ("&tog", "TG"), ("&to", "TO"), ("&sl", "OSL")];
+
+///read the keymap node of a ZMK .keymap file, one layer per child node with bindings
+pub fn get_zmk_keymap(source: &str, ops: &PrintOptions) -> Result<Keymap, MyError> {
+    let mut layers = vec![];
+    for (name, bindings) in zmk_layers(source)? {
+        let rows = bindings
+            .into_inner()
+            .map(|row| Item::Item(row.into_inner().map(binding_string).to_vec()))
+            .to_vec();
+        //ZMK layers are numbered in order
+        let num = layers.len().to_string();
+        let split = ops.board.is_split(&name);
+        layers.push(Item::Item(Layer::new(num, name, rows, split, ops)?));
+    }
+    let mut keymap = Keymap::new(layers, ops)?;
+    //for #define layer names
+    keymap.context = Context::from_source(source);
+    Ok(keymap)
+}
+
+///the .keymap file with each bindings block aligned in columns, everything else is kept as it is
+pub fn zmk_string(source: &str, ops: &PrintOptions) -> Result<String, MyError> {
+    let keymap = get_zmk_keymap(source, ops)?;
+    let layouts = layers_string(&keymap, ops, " ");
+    let mut result = String::new();
+    let mut last = 0;
+    for ((_, bindings), layout) in zmk_layers(source)?.into_iter().zip(layouts) {
+        let start = bindings.as_span().start();
+        let line_start = source[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let indent = &source[line_start..start];
+        let indent = if indent.trim().is_empty() { indent } else { "" };
+        let lines = layout.lines().to_vec();
+        let (above, after) = binding_comments(source, &bindings, lines.len())?;
+        result.push_str(&source[last..start]);
+        result.push_str("bindings = <\n");
+        for (i, line) in lines.iter().enumerate() {
+            for comment in &above[i] {
+                result.push_str(&format!("{indent}{comment}\n"));
+            }
+            let line = line.trim_end();
+            match after[i].join(" ") {
+                trailing if trailing.is_empty() => result.push_str(&format!("{indent}{line}\n")),
+                trailing => result.push_str(&format!("{indent}{line} {trailing}\n")),
+            }
+        }
+        for comment in &above[lines.len()] {
+            result.push_str(&format!("{indent}{comment}\n"));
+        }
+        result.push_str(&format!("{indent}>;"));
+        last = bindings.as_span().end();
+    }
+    result.push_str(&source[last..]);
+    Ok(result)
+}
+
+//comments on their own lines above each row, e.g. // ╭───┬───╮ art, and comments at the end of each row
+type RowComments = (Vec<Vec<String>>, Vec<Vec<String>>);
+fn binding_comments(
+    source: &str,
+    bindings: &Pair<Rule>,
+    lines: usize,
+) -> Result<RowComments, MyError> {
+    let rows = bindings
+        .clone()
+        .into_inner()
+        .filter(|p| p.as_rule() == Rule::zmkrow)
+        .map(|p| (p.as_span().start(), p.as_span().end()))
+        .to_vec();
+    let mut above = vec![vec![]; rows.len() + 1];
+    let mut after = vec![vec![]; rows.len()];
+    for (offset, comment) in comments(bindings.as_str()) {
+        let at = bindings.as_span().start() + offset;
+        let row = rows.iter().filter(|(start, _)| *start < at).count();
+        match row.checked_sub(1).map(|r| rows[r]) {
+            Some((_, end)) if at < end => {
+                return Err(format!(
+                    "Can't keep the comment {comment} in the middle of a row of bindings"
+                )
+                .into())
+            }
+            Some((_, end)) if !source[end..at].contains('\n') => after[row - 1].push(comment),
+            _ => above[row].push(comment),
+        }
+    }
+    //each row is one line of the tidy bindings
+    if rows.len() != lines && (above.iter().chain(&after).any(|c| !c.is_empty())) {
+        return Err("Can't keep the comments in these bindings"
+            .to_string()
+            .into());
+    }
+    above.resize(lines + 1, vec![]);
+    after.resize(lines, vec![]);
+    Ok((above, after))
+}
+
+//the name and bindings of each layer in the keymap node
+fn zmk_layers(source: &str) -> Result<Vec<(String, Pair<'_, Rule>)>, MyError> {
+    let pairs = MyParser::parse(Rule::zmkouter, source)?;
+    let Some(node) = pairs.into_iter().find(|p| p.as_rule() == Rule::zmkkeymap) else {
+        return Err("No keymap node found".to_string().into());
+    };
+    let mut layers = vec![];
+    for child in node.into_inner() {
+        if child.as_rule() != Rule::zmklayer {
+            continue;
+        }
+        let mut inner = child.into_inner();
+        let name = inner.next().unwrap().as_str().to_string();
+        if let Some(bindings) = inner.find(|p| p.as_rule() == Rule::zmkbindings) {
+            layers.push((name, bindings));
+        }
+    }
+    Ok(layers)
+}
+
+//e.g. "&mt   LSHIFT A" to "&mt LSHIFT A"
+fn binding_string(pair: Pair<Rule>) -> String {
+    pair.into_inner().map(|p| p.as_str()).to_vec().join(" ")
+}
+
+///the QMK equivalent of a ZMK binding, e.g. &mt LSHIFT A to LSFT_T(KC_A)
+pub fn qmk_code(binding: &str) -> Option<String> {
+    let mut parts = binding.split_whitespace();
+    let behaviour = parts.next()?;
+    if !behaviour.starts_with('&') {
+        return None;
+    }
+    let params = parts.to_vec();
+    if let Some((_, qmk)) = SIMPLE.iter().find(|(z, _)| *z == behaviour) {
+        return params.is_empty().then(|| qmk.to_string());
+    }
+    if let Some((_, qmk)) = LAYER_BEHAVIOURS.iter().find(|(z, _)| *z == behaviour) {
+        return match params.as_slice() {
+            [layer] => Some(format!("{qmk}({layer})")),
+            _ => None,
+        };
+    }
+    match (behaviour, params.as_slice()) {
+        ("&kp", [key]) => qmk_key(key),
+        ("&mt", [modifier, key]) => {
+            let modifier = qmk_modifier(modifier)?;
+            Some(format!("{modifier}_T({})", qmk_key(key)?))
+        }
+        ("&lt", [layer, key]) => Some(format!("LT({layer},{})", qmk_key(key)?)),
+        ("&sk", [modifier]) => Some(format!("OSM(MOD_{})", qmk_modifier(modifier)?)),
+        _ => None,
+    }
+}
+
+//e.g. LSHIFT to LSFT
+fn qmk_modifier(key: &str) -> Option<String> {
+    let modifier = qmk_key(key)?.strip_prefix("KC_")?.to_string();
+    let modifiers = [
+        "LSFT", "LCTL", "LALT", "LGUI", "RSFT", "RCTL", "RALT", "RGUI",
+    ];
+    modifiers.contains(&modifier.as_str()).then_some(modifier)
+}
+
+//e.g. A to KC_A, N1 to KC_1, LS(TAB) to LSFT(KC_TAB)
+fn qmk_key(key: &str) -> Option<String> {
+    if let Some((function, inner)) = key.strip_suffix(')').and_then(|k| k.split_once('(')) {
+        let (_, qmk) = MOD_FUNCTIONS.iter().find(|(z, _)| *z == function)?;
+        return Some(format!("{qmk}({})", qmk_key(inner)?));
+    }
+    if let Some((_, qmk)) = KEYS.iter().find(|(z, _)| *z == key) {
+        return Some(qmk.to_string());
+    }
+    let digit = |n: &str| n.len() == 1 && n.chars().all(|c| c.is_ascii_digit());
+    if key.len() == 1 && key.chars().all(|c| c.is_ascii_uppercase()) {
+        Some(format!("KC_{key}"))
+    } else if let Some(n) = key.strip_prefix("NUMBER_").or(key.strip_prefix('N')) {
+        digit(n).then(|| format!("KC_{n}"))
+    } else if let Some(n) = key.strip_prefix("KP_NUMBER_").or(key.strip_prefix("KP_N")) {
+        digit(n).then(|| format!("KC_P{n}"))
+    } else if let Some(n) = key.strip_prefix('F') {
+        n.parse::<u8>()
+            .ok()
+            .filter(|n| (1..=24).contains(n))
+            .map(|n| format!("KC_F{n}"))
+    } else {
+        None
+    }
+}
+
+///a ZMK keymap node for the active layers and rows of a QMK keymap,
+///and a warning for each key with no ZMK equivalent, which is left as &none
+pub fn qmk_to_zmk(keymap: &Keymap, ops: &PrintOptions) -> Result<(String, Vec<String>), MyError> {
+    let active = keymap.active_layers();
+    //ZMK layers are numbered, so QMK layer names become their position
+    let layer_nums = active.iter().map(|l| l.num.clone()).to_vec();
+    let mut warnings = vec![];
+    let mut layers = vec![];
+    for (layi, layer) in active.iter().enumerate() {
+        let rows = layer
+            .active_keys()
+            .into_iter()
+            .map(|row| {
+                let bindings = row
+                    .iter()
+                    .flatten()
+                    .map(|code| {
+                        let expanded = keymap.context.expand(code);
+                        zmk_binding(&expanded, &layer_nums).unwrap_or_else(|| {
+                            warnings
+                                .push(format!("Layer {}: no ZMK equivalent for {code}", layer.num));
+                            "&none".to_string()
+                        })
+                    })
+                    .to_vec();
+                Item::Item(bindings)
+            })
+            .to_vec();
+        let name = layer.ident();
+        let split = layer.split;
+        layers.push(Item::Item(Layer::new(
+            layi.to_string(),
+            name,
+            rows,
+            split,
+            ops,
+        )?));
+    }
+    let zmk = Keymap::new(layers, ops)?;
+    let mut result = String::from("keymap {\n    compatible = \"zmk,keymap\";\n");
+    for (layer, layout) in zmk.layers.iter().zip(layers_string(&zmk, ops, " ")) {
+        result.push_str(&format!(
+            "\n    {} {{\n        bindings = <\n",
+            layer.name()
+        ));
+        for line in layout.lines() {
+            result.push_str(&format!("        {}\n", line.trim_end()));
+        }
+        result.push_str("        >;\n    };\n");
+    }
+    result.push_str("};\n");
+    Ok((result, warnings))
+}
+
+//the ZMK binding for a QMK key, e.g. LSFT_T(KC_A) to &mt LSHFT A
+fn zmk_binding(code: &str, layer_nums: &[String]) -> Option<String> {
+    let code = short_keycode(code);
+    if let Some((zmk, _)) = SIMPLE.iter().find(|(_, qmk)| *qmk == code) {
+        return Some(zmk.to_string());
+    }
+    let layer = |l: &String| match layer_nums.iter().position(|n| n == l) {
+        Some(i) => i.to_string(),
+        None => l.clone(),
+    };
+    let Some((name, params)) = split_call(code) else {
+        return Some(format!("&kp {}", zmk_key(code)?));
+    };
+    if let Some((zmk, _)) = LAYER_BEHAVIOURS.iter().find(|(_, qmk)| *qmk == name) {
+        return match params.as_slice() {
+            [l] => Some(format!("{zmk} {}", layer(l))),
+            _ => None,
+        };
+    }
+    match (name.as_str(), params.as_slice()) {
+        ("LT", [l, key]) => Some(format!("&lt {} {}", layer(l), zmk_key(key)?)),
+        ("MT", [modifier, key]) => {
+            let modifier = zmk_key(&format!("KC_{}", modifier.strip_prefix("MOD_")?))?;
+            Some(format!("&mt {modifier} {}", zmk_key(key)?))
+        }
+        ("OSM", [modifier]) => {
+            let modifier = zmk_key(&format!("KC_{}", modifier.strip_prefix("MOD_")?))?;
+            Some(format!("&sk {modifier}"))
+        }
+        (name, [key]) if name.ends_with("_T") => {
+            let modifier = zmk_key(&format!("KC_{}", name.strip_suffix("_T")?))?;
+            Some(format!("&mt {modifier} {}", zmk_key(key)?))
+        }
+        _ => Some(format!("&kp {}", zmk_key(code)?)),
+    }
+}
+
+//e.g. KC_A to A, KC_1 to N1, LSFT(KC_TAB) to LS(TAB)
+fn zmk_key(code: &str) -> Option<String> {
+    let code = short_keycode(code);
+    if let Some((name, params)) = split_call(code) {
+        //S(KC_A) is short for LSFT(KC_A)
+        let name = match name.as_str() {
+            "S" => "LSFT",
+            "C" => "LCTL",
+            "A" => "LALT",
+            "G" => "LGUI",
+            name => name,
+        };
+        let (zmk, _) = MOD_FUNCTIONS.iter().find(|(_, qmk)| *qmk == name)?;
+        return match params.as_slice() {
+            [key] => Some(format!("{zmk}({})", zmk_key(key)?)),
+            _ => None,
+        };
+    }
+    if let Some((zmk, _)) = KEYS.iter().find(|(_, qmk)| *qmk == code) {
+        return Some(zmk.to_string());
+    }
+    let rest = code.strip_prefix("KC_")?;
+    let digit = |n: &str| n.len() == 1 && n.chars().all(|c| c.is_ascii_digit());
+    if rest.len() == 1 && rest.chars().all(|c| c.is_ascii_uppercase()) {
+        Some(rest.to_string())
+    } else if digit(rest) {
+        Some(format!("N{rest}"))
+    } else if let Some(n) = rest.strip_prefix("KP_").or(rest.strip_prefix('P')) {
+        digit(n).then(|| format!("KP_N{n}"))
+    } else if rest.starts_with('F') && qmk_key(rest).is_some() {
+        Some(rest.to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::{layer_switch, nice_code};
+
+    #[test]
+    fn zmk_keymap() {
+        let source = r#"
+#include <behaviors.dtsi>
+#define NAV 1
+/ {
+    keymap {
+        compatible = "zmk,keymap";
+        base {
+            display-name = "Base";
+            bindings = <
+  &kp Q  &kp W   &kp E // top row
+  &mt LSHIFT A &kp LS(N1)   &lt NAV SPACE
+            >;
+        };
+        nav_layer {
+            bindings = <
+  &trans &none &bt BT_CLR
+  &mo NAV &kp C_VOL_UP &kp LEFT
+            >;
+        };
+    };
+};
+"#;
+        let ops = PrintOptions {
+            thumb_shift_in: 0,
+            ..Default::default()
+        };
+        let keymap = get_zmk_keymap(source, &ops).unwrap();
+        assert_eq!(keymap.layers.len(), 2);
+        assert_eq!(keymap.layers[1].num, "1");
+        let keys = &keymap.layers[0].keys;
+        assert_eq!(keys[1][0].as_deref(), Some("&mt LSHIFT A"));
+
+        let ctx = &keymap.context;
+        assert_eq!(ctx.expand("&mt LSHIFT A"), "LSFT_T(KC_A)");
+        assert_eq!(ctx.expand("&kp LS(N1)"), "LSFT(KC_1)");
+        assert_eq!(ctx.expand("&lt NAV SPACE"), "LT(1,KC_SPC)");
+        assert_eq!(nice_code("&kp Q", ctx).middle, "Q");
+        assert_eq!(nice_code("&mt LSHIFT A", ctx).bottom, "⇧");
+        assert_eq!(nice_code("&trans", ctx).middle, "⇄");
+        assert_eq!(nice_code("&bt BT_CLR", ctx).middle, "BT_CLR");
+        assert_eq!(
+            layer_switch("&mo NAV", ctx),
+            Some(("MO".to_string(), "1".to_string()))
+        );
+    }
+
+    #[test]
+    fn zmk_format() {
+        let source = r#"keymap {
+    base {
+        display-name = "Base";
+        bindings = <&kp Q &kp W
+ &kp LSHIFT   &kp A
+        >;
+    };
+};
+"#;
+        let ops = PrintOptions {
+            thumb_shift_in: 0,
+            ..Default::default()
+        };
+        let out = zmk_string(source, &ops).unwrap();
+        assert_eq!(
+            out,
+            r#"keymap {
+    base {
+        display-name = "Base";
+        bindings = <
+              &kp Q      &kp W
+         &kp LSHIFT      &kp A
+        >;
+    };
+};
+"#
+        );
+        assert_eq!(zmk_string(&out, &ops).unwrap(), out);
+    }
+
+    #[test]
+    fn zmk_format_comments() {
+        let source = r#"keymap {
+    base {
+        bindings = <
+// ╭──────┬──────╮
+  &kp Q &kp W // top
+// ├──────┼──────┤
+ &kp LSHIFT   &kp A
+// ╰──────┴──────╯
+        >;
+    };
+};
+"#;
+        let ops = PrintOptions {
+            thumb_shift_in: 0,
+            ..Default::default()
+        };
+        let out = zmk_string(source, &ops).unwrap();
+        assert_eq!(
+            out,
+            r#"keymap {
+    base {
+        bindings = <
+        // ╭──────┬──────╮
+              &kp Q      &kp W // top
+        // ├──────┼──────┤
+         &kp LSHIFT      &kp A
+        // ╰──────┴──────╯
+        >;
+    };
+};
+"#
+        );
+        assert_eq!(zmk_string(&out, &ops).unwrap(), out);
+        let inside = source.replace("&kp Q &kp W", "&kp Q /* q */ &kp W");
+        assert_eq!(
+            zmk_string(&inside, &ops).err().unwrap().to_string(),
+            "Can't keep the comment /* q */ in the middle of a row of bindings"
+        );
+    }
+
+    #[test]
+    fn qmk_conversion() {
+        let example = r#"
+            [_BASE] = LAYOUT(LSFT_T(KC_A), LT(_NAV, KC_SPC), S(KC_1), RGB_TOG),
+            [_NAV] = LAYOUT(_______, XXXXXXX, MO(_BASE), OSM(MOD_LCTL)),
+        "#;
+        let ops = PrintOptions {
+            thumb_shift_in: 0,
+            ..Default::default()
+        };
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        let keymap = crate::myparser::get_keymap(prog.next().unwrap(), &ops).unwrap();
+        let (zmk, warnings) = qmk_to_zmk(&keymap, &ops).unwrap();
+        assert_eq!(
+            zmk,
+            r#"keymap {
+    compatible = "zmk,keymap";
+
+    base {
+        bindings = <
+         &mt LSHFT A  &lt 1 SPACE      &kp LS(N1)  &none
+        >;
+    };
+
+    nav {
+        bindings = <
+              &trans        &none      &mo 0       &sk LCTRL
+        >;
+    };
+};
+"#
+        );
+        assert_eq!(warnings, vec!["Layer _BASE: no ZMK equivalent for RGB_TOG"]);
+
+        //a board in one piece stays in one piece, though the ZMK node name doesn't say so
+        let example = "[0] = LAYOUT_ortho_2x4(KC_Q, KC_W, KC_E, KC_R,\nKC_A, KC_S, KC_D, KC_F)";
+        let mut prog = MyParser::parse(Rule::programouter, example).unwrap();
+        let keymap = crate::myparser::get_keymap(prog.next().unwrap(), &ops).unwrap();
+        let (zmk, _) = qmk_to_zmk(&keymap, &ops).unwrap();
+        assert!(
+            zmk.contains("        &kp Q  &kp W  &kp E  &kp R\n"),
+            "{zmk}"
+        );
+    }
+}